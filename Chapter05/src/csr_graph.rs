@@ -0,0 +1,84 @@
+use crate::graph::{Edge, GraphAccess, InternetOfThings};
+use std::collections::HashMap;
+
+type KeyType = u64;
+
+/// An immutable graph in compressed sparse row layout: the edges of node
+/// `i` are `edges[offsets[i]..offsets[i + 1]]`.
+pub struct CsrGraph {
+    offsets: Vec<usize>,
+    edges: Vec<Edge>,
+    nodes: Vec<KeyType>,
+    index: HashMap<KeyType, usize>,
+    negative_edges: usize,
+    directed: bool,
+}
+
+impl CsrGraph {
+    pub fn freeze(graph: &InternetOfThings) -> CsrGraph {
+        let mut offsets = Vec::with_capacity(graph.node_count() + 1);
+        let mut edges = Vec::with_capacity(graph.edges() as usize);
+        let mut nodes = Vec::with_capacity(graph.node_count());
+        offsets.push(0);
+        for i in 0..graph.node_count() {
+            edges.extend_from_slice(graph.out_edges(i));
+            offsets.push(edges.len());
+            nodes.push(graph.node_key(i));
+        }
+        let index = nodes.iter().enumerate().map(|(i, n)| (*n, i)).collect();
+        let negative_edges = edges.iter().filter(|e| e.weight < 0).count();
+        CsrGraph {
+            offsets,
+            edges,
+            nodes,
+            index,
+            negative_edges,
+            directed: graph.is_directed(),
+        }
+    }
+
+    pub fn nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_directed(&self) -> bool {
+        self.directed
+    }
+
+    /// Counts each undirected edge once, like `InternetOfThings::edges`.
+    pub fn edges(&self) -> u64 {
+        if self.directed {
+            self.edges.len() as u64
+        } else {
+            (0..self.nodes.len())
+                .map(|u| self.out_edges(u).iter().filter(|e| e.node >= u).count() as u64)
+                .sum()
+        }
+    }
+
+    pub fn neighbors(&self, node: KeyType) -> Option<&[Edge]> {
+        self.index.get(&node).map(|i| self.out_edges(*i))
+    }
+}
+
+impl GraphAccess for CsrGraph {
+    fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn node_key(&self, index: usize) -> KeyType {
+        self.nodes[index]
+    }
+
+    fn node_index(&self, node: KeyType) -> Option<usize> {
+        self.index.get(&node).cloned()
+    }
+
+    fn out_edges(&self, index: usize) -> &[Edge] {
+        &self.edges[self.offsets[index]..self.offsets[index + 1]]
+    }
+
+    fn has_negative_weights(&self) -> bool {
+        self.negative_edges > 0
+    }
+}
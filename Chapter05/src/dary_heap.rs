@@ -0,0 +1,141 @@
+use crate::heap::{Handle, PriorityQueue};
+use crate::MessageNotification;
+
+pub struct DAryHeap {
+    pub length: usize,
+    arity: usize,
+    heap: Vec<(Handle, MessageNotification)>,
+    positions: Vec<Option<usize>>,
+    // handles of popped notifications, to be handed out again
+    free: Vec<Handle>,
+}
+
+impl DAryHeap {
+    pub fn new_empty(arity: usize) -> DAryHeap {
+        assert!(arity >= 2, "a heap needs an arity of at least 2");
+        DAryHeap {
+            length: 0,
+            arity,
+            heap: vec![],
+            positions: vec![],
+            free: vec![],
+        }
+    }
+
+    pub fn arity(&self) -> usize {
+        self.arity
+    }
+
+    fn has_more_messages(&self, pos1: usize, pos2: usize) -> bool {
+        self.heap[pos1].1.no_messages >= self.heap[pos2].1.no_messages
+    }
+
+    fn swap(&mut self, pos1: usize, pos2: usize) {
+        self.heap.swap(pos1, pos2);
+        self.positions[self.heap[pos1].0] = Some(pos1);
+        self.positions[self.heap[pos2].0] = Some(pos2);
+    }
+
+    fn sift_up(&mut self, mut i: usize) {
+        while i > 0 {
+            let parent = (i - 1) / self.arity;
+            if self.has_more_messages(parent, i) {
+                break;
+            }
+            self.swap(i, parent);
+            i = parent;
+        }
+    }
+
+    fn sift_down(&mut self, mut i: usize) {
+        loop {
+            let first = i * self.arity + 1;
+            let last = (first + self.arity).min(self.length);
+            if first >= last {
+                break;
+            }
+            let child = (first + 1..last).fold(first, |max, c| {
+                if self.has_more_messages(max, c) {
+                    max
+                } else {
+                    c
+                }
+            });
+            if self.has_more_messages(i, child) {
+                break;
+            }
+            self.swap(i, child);
+            i = child;
+        }
+    }
+
+    pub fn is_valid_heap(&self) -> bool {
+        self.length == self.heap.len()
+            && (1..self.length).all(|i| self.has_more_messages((i - 1) / self.arity, i))
+            && self
+                .heap
+                .iter()
+                .enumerate()
+                .all(|(i, e)| self.positions[e.0] == Some(i))
+    }
+}
+
+impl PriorityQueue for DAryHeap {
+    fn push(&mut self, notification: MessageNotification) -> Handle {
+        let handle = match self.free.pop() {
+            Some(handle) => {
+                self.positions[handle] = Some(self.length);
+                handle
+            }
+            None => {
+                self.positions.push(Some(self.length));
+                self.positions.len() - 1
+            }
+        };
+        self.heap.push((handle, notification));
+        self.length = self.heap.len();
+        self.sift_up(self.length - 1);
+        handle
+    }
+
+    fn pop(&mut self) -> Option<MessageNotification> {
+        if self.length > 0 {
+            let (handle, elem) = self.heap.swap_remove(0);
+            self.positions[handle] = None;
+            self.free.push(handle);
+            self.length = self.heap.len();
+            if self.length > 0 {
+                self.positions[self.heap[0].0] = Some(0);
+                self.sift_down(0);
+            }
+            Some(elem)
+        } else {
+            None
+        }
+    }
+
+    fn peek(&self) -> Option<&MessageNotification> {
+        self.heap.first().map(|e| &e.1)
+    }
+
+    fn decrease_key(&mut self, handle: Handle, no_messages: u64) -> bool {
+        match self.positions.get(handle) {
+            Some(&Some(i)) if self.heap[i].1.no_messages <= no_messages => {
+                self.heap[i].1.no_messages = no_messages;
+                self.sift_up(i);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn merge(&mut self, other: DAryHeap) {
+        for (_, notification) in other.heap {
+            self.push(notification);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.length
+    }
+}
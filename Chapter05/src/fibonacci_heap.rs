@@ -0,0 +1,315 @@
+use crate::heap::{Handle, PriorityQueue};
+use crate::MessageNotification;
+
+struct Node {
+    notification: MessageNotification,
+    parent: Option<usize>,
+    child: Option<usize>,
+    // siblings form a circular, doubly linked list
+    left: usize,
+    right: usize,
+    degree: usize,
+    marked: bool,
+}
+
+pub struct FibonacciHeap {
+    pub length: usize,
+    max: Option<usize>,
+    nodes: Vec<Option<Node>>,
+    // slots of popped nodes, to be handed out again
+    free: Vec<usize>,
+}
+
+impl FibonacciHeap {
+    pub fn new_empty() -> FibonacciHeap {
+        FibonacciHeap {
+            length: 0,
+            max: None,
+            nodes: vec![],
+            free: vec![],
+        }
+    }
+
+    fn allocate(&mut self, node: Node) -> usize {
+        match self.free.pop() {
+            Some(i) => {
+                self.nodes[i] = Some(node);
+                i
+            }
+            None => {
+                self.nodes.push(Some(node));
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    fn node(&self, i: usize) -> &Node {
+        self.nodes[i].as_ref().expect("Node was already removed")
+    }
+
+    fn node_mut(&mut self, i: usize) -> &mut Node {
+        self.nodes[i].as_mut().expect("Node was already removed")
+    }
+
+    fn has_more_messages(&self, a: usize, b: usize) -> bool {
+        self.node(a).notification.no_messages >= self.node(b).notification.no_messages
+    }
+
+    fn siblings(&self, start: usize) -> Vec<usize> {
+        let mut result = vec![start];
+        let mut current = self.node(start).right;
+        while current != start {
+            result.push(current);
+            current = self.node(current).right;
+        }
+        result
+    }
+
+    fn unlink(&mut self, x: usize) {
+        let (left, right) = (self.node(x).left, self.node(x).right);
+        self.node_mut(left).right = right;
+        self.node_mut(right).left = left;
+        let node = self.node_mut(x);
+        node.left = x;
+        node.right = x;
+    }
+
+    fn splice_after(&mut self, a: usize, x: usize) {
+        let b = self.node(a).right;
+        {
+            let node = self.node_mut(x);
+            node.left = a;
+            node.right = b;
+        }
+        self.node_mut(a).right = x;
+        self.node_mut(b).left = x;
+    }
+
+    fn add_root(&mut self, x: usize) {
+        {
+            let node = self.node_mut(x);
+            node.parent = None;
+            node.marked = false;
+        }
+        match self.max {
+            Some(max) => {
+                self.splice_after(max, x);
+                if !self.has_more_messages(max, x) {
+                    self.max = Some(x);
+                }
+            }
+            None => {
+                let node = self.node_mut(x);
+                node.left = x;
+                node.right = x;
+                self.max = Some(x);
+            }
+        }
+    }
+
+    fn link(&mut self, child: usize, parent: usize) {
+        match self.node(parent).child {
+            Some(first) => self.splice_after(first, child),
+            None => {
+                let node = self.node_mut(child);
+                node.left = child;
+                node.right = child;
+                self.node_mut(parent).child = Some(child);
+            }
+        }
+        {
+            let node = self.node_mut(child);
+            node.parent = Some(parent);
+            node.marked = false;
+        }
+        self.node_mut(parent).degree += 1;
+    }
+
+    fn consolidate(&mut self, start: usize) {
+        let mut by_degree: Vec<Option<usize>> = vec![];
+        for root in self.siblings(start) {
+            let mut x = root;
+            let mut degree = self.node(x).degree;
+            while let Some(Some(y)) = by_degree.get(degree).cloned() {
+                let (parent, child) = if self.has_more_messages(x, y) {
+                    (x, y)
+                } else {
+                    (y, x)
+                };
+                self.link(child, parent);
+                by_degree[degree] = None;
+                x = parent;
+                degree += 1;
+            }
+            if by_degree.len() <= degree {
+                by_degree.resize(degree + 1, None);
+            }
+            by_degree[degree] = Some(x);
+        }
+
+        self.max = None;
+        for root in by_degree.into_iter().flatten() {
+            self.add_root(root);
+        }
+    }
+
+    fn cut(&mut self, x: usize, parent: usize) {
+        if self.node(x).right == x {
+            self.node_mut(parent).child = None;
+        } else {
+            if self.node(parent).child == Some(x) {
+                self.node_mut(parent).child = Some(self.node(x).right);
+            }
+            self.unlink(x);
+        }
+        self.node_mut(parent).degree -= 1;
+        self.add_root(x);
+    }
+
+    fn cascading_cut(&mut self, mut y: usize) {
+        while let Some(parent) = self.node(y).parent {
+            if !self.node(y).marked {
+                self.node_mut(y).marked = true;
+                break;
+            }
+            self.cut(y, parent);
+            y = parent;
+        }
+    }
+
+    pub fn is_valid_heap(&self) -> bool {
+        let roots = match self.max {
+            Some(max) => self.siblings(max),
+            None => return self.length == 0,
+        };
+        let mut count = 0;
+        let mut stack: Vec<(usize, Option<usize>)> = roots.into_iter().map(|r| (r, None)).collect();
+        while let Some((i, parent)) = stack.pop() {
+            count += 1;
+            let node = self.node(i);
+            let ordered = match parent {
+                Some(p) => self.has_more_messages(p, i),
+                None => self.has_more_messages(self.max.unwrap(), i),
+            };
+            if node.parent != parent || !ordered {
+                return false;
+            }
+            if let Some(child) = node.child {
+                let children = self.siblings(child);
+                if children.len() != node.degree {
+                    return false;
+                }
+                stack.extend(children.into_iter().map(|c| (c, Some(i))));
+            }
+        }
+        count == self.length
+    }
+}
+
+impl PriorityQueue for FibonacciHeap {
+    fn push(&mut self, notification: MessageNotification) -> Handle {
+        // `add_root` links the node to itself or the other roots
+        let handle = self.allocate(Node {
+            notification,
+            parent: None,
+            child: None,
+            left: 0,
+            right: 0,
+            degree: 0,
+            marked: false,
+        });
+        self.add_root(handle);
+        self.length += 1;
+        handle
+    }
+
+    fn pop(&mut self) -> Option<MessageNotification> {
+        self.max.map(|max| {
+            if let Some(child) = self.node(max).child {
+                for c in self.siblings(child) {
+                    self.splice_after(max, c);
+                    let node = self.node_mut(c);
+                    node.parent = None;
+                    node.marked = false;
+                }
+            }
+            let next = self.node(max).right;
+            self.unlink(max);
+            if next == max {
+                self.max = None;
+            } else {
+                self.consolidate(next);
+            }
+            self.length -= 1;
+            self.free.push(max);
+            self.nodes[max]
+                .take()
+                .expect("Node was already removed")
+                .notification
+        })
+    }
+
+    fn peek(&self) -> Option<&MessageNotification> {
+        self.max.map(|max| &self.node(max).notification)
+    }
+
+    fn decrease_key(&mut self, handle: Handle, no_messages: u64) -> bool {
+        match self.nodes.get(handle) {
+            Some(Some(node)) if node.notification.no_messages <= no_messages => {
+                self.node_mut(handle).notification.no_messages = no_messages;
+                match self.node(handle).parent {
+                    Some(parent) if !self.has_more_messages(parent, handle) => {
+                        self.cut(handle, parent);
+                        self.cascading_cut(parent);
+                    }
+                    _ => {
+                        let max = self.max.expect("A non-empty heap has a maximum");
+                        if !self.has_more_messages(max, handle) {
+                            self.max = Some(handle);
+                        }
+                    }
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn merge(&mut self, other: FibonacciHeap) {
+        let offset = self.nodes.len();
+        self.nodes.extend(other.nodes.into_iter().map(|n| {
+            n.map(|n| Node {
+                notification: n.notification,
+                parent: n.parent.map(|p| p + offset),
+                child: n.child.map(|c| c + offset),
+                left: n.left + offset,
+                right: n.right + offset,
+                degree: n.degree,
+                marked: n.marked,
+            })
+        }));
+        self.free.extend(other.free.into_iter().map(|i| i + offset));
+        if let Some(other_max) = other.max.map(|m| m + offset) {
+            match self.max {
+                Some(max) => {
+                    // concatenate both root lists
+                    let max_right = self.node(max).right;
+                    let other_left = self.node(other_max).left;
+                    self.node_mut(max).right = other_max;
+                    self.node_mut(other_max).left = max;
+                    self.node_mut(other_left).right = max_right;
+                    self.node_mut(max_right).left = other_left;
+                    if !self.has_more_messages(max, other_max) {
+                        self.max = Some(other_max);
+                    }
+                }
+                None => self.max = Some(other_max),
+            }
+        }
+        self.length += other.length;
+    }
+
+    fn len(&self) -> usize {
+        self.length
+    }
+}
@@ -0,0 +1,278 @@
+use crate::graph::{DisjointSet, GraphError};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::hash::Hash;
+use std::ops::Add;
+
+/// Anything that can be summed up along a path and compared.
+pub trait Weight: Copy + PartialOrd + Add<Output = Self> {
+    fn zero() -> Self;
+}
+
+macro_rules! impl_weight {
+    ($($t:ty),*) => {
+        $(impl Weight for $t {
+            fn zero() -> $t {
+                0 as $t
+            }
+        })*
+    };
+}
+
+impl_weight!(u8, u16, u32, u64, usize, i8, i16, i32, i64, f32, f64);
+
+// reversed, so a `BinaryHeap` pops the lowest cost first
+struct State<W> {
+    cost: W,
+    node: usize,
+}
+
+impl<W: Weight> PartialEq for State<W> {
+    fn eq(&self, other: &State<W>) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<W: Weight> Eq for State<W> {}
+
+impl<W: Weight> Ord for State<W> {
+    fn cmp(&self, other: &State<W>) -> Ordering {
+        other
+            .cost
+            .partial_cmp(&self.cost)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| other.node.cmp(&self.node))
+    }
+}
+
+impl<W: Weight> PartialOrd for State<W> {
+    fn partial_cmp(&self, other: &State<W>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Dijkstra's algorithm over node indices, for `Graph` as well as the
+/// `GraphAccess` graphs. Edges that `weight` has no value for are left out.
+pub(crate) fn dijkstra<'a, W, E, I>(
+    nodes: usize,
+    src: usize,
+    dest: Option<usize>,
+    out_edges: impl Fn(usize) -> I,
+    weight: impl Fn(usize, &E) -> Option<W>,
+) -> (Vec<Option<W>>, Vec<Option<usize>>)
+where
+    W: Weight,
+    E: 'a,
+    I: Iterator<Item = (usize, &'a E)>,
+{
+    let mut distance = vec![None; nodes];
+    distance[src] = Some(W::zero());
+    let mut parent = vec![None; nodes];
+    let mut closed = vec![false; nodes];
+
+    let mut open = BinaryHeap::new();
+    open.push(State {
+        cost: W::zero(),
+        node: src,
+    });
+    while let Some(State { cost, node: u }) = open.pop() {
+        if closed[u] {
+            continue;
+        }
+        closed[u] = true;
+        if Some(u) == dest {
+            break;
+        }
+
+        for (v, w) in out_edges(u).filter_map(|(v, e)| weight(u, e).map(|w| (v, w))) {
+            let new_distance = cost + w;
+            let shorter = match distance[v] {
+                Some(d) => new_distance < d,
+                None => true,
+            };
+            if shorter {
+                distance[v] = Some(new_distance);
+                parent[v] = Some(u);
+                open.push(State {
+                    cost: new_distance,
+                    node: v,
+                });
+            }
+        }
+    }
+    (distance, parent)
+}
+
+/// Kruskal's algorithm over `(weight, from, to)` edges between node indices,
+/// treating them as undirected. Equal weights are taken in node order.
+pub(crate) fn kruskal<W: Weight>(
+    nodes: usize,
+    mut edges: Vec<(W, usize, usize)>,
+) -> (W, Vec<(usize, usize, W)>) {
+    edges.sort_by(|a, b| {
+        a.0.partial_cmp(&b.0)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| (a.1, a.2).cmp(&(b.1, b.2)))
+    });
+
+    let mut components = DisjointSet::new(nodes);
+    let mut total = W::zero();
+    let mut tree = vec![];
+    for (w, u, v) in edges {
+        if components.union(u, v) {
+            total = total + w;
+            tree.push((u, v, w));
+        }
+    }
+    (total, tree)
+}
+
+pub struct Graph<N, E> {
+    adjacency_list: Vec<Vec<(usize, E)>>,
+    nodes: Vec<N>,
+    index: HashMap<N, usize>,
+}
+
+impl<N, E> Graph<N, E>
+where
+    N: Hash + Eq + Clone,
+{
+    pub fn new() -> Graph<N, E> {
+        Graph {
+            adjacency_list: vec![],
+            nodes: vec![],
+            index: HashMap::new(),
+        }
+    }
+
+    fn require_node_index(&self, node: &N) -> Result<usize, GraphError<N>> {
+        self.index
+            .get(node)
+            .cloned()
+            .ok_or_else(|| GraphError::UnknownNode(node.clone()))
+    }
+
+    pub fn nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn edges(&self) -> usize {
+        self.adjacency_list.iter().map(|e| e.len()).sum()
+    }
+
+    pub fn add_node(&mut self, node: N) -> Result<(), GraphError<N>> {
+        if self.index.contains_key(&node) {
+            return Err(GraphError::DuplicateNode(node));
+        }
+        self.index.insert(node.clone(), self.nodes.len());
+        self.nodes.push(node);
+        self.adjacency_list.push(vec![]);
+        Ok(())
+    }
+
+    pub fn add_edge(&mut self, from: N, to: N, payload: E) -> Result<(), GraphError<N>> {
+        let u = self.require_node_index(&from)?;
+        let v = self.require_node_index(&to)?;
+        if self.adjacency_list[u].iter().any(|e| e.0 == v) {
+            return Err(GraphError::DuplicateEdge(from, to));
+        }
+        self.adjacency_list[u].push((v, payload));
+        Ok(())
+    }
+
+    pub fn edge(&self, from: &N, to: &N) -> Option<&E> {
+        let u = *self.index.get(from)?;
+        let v = *self.index.get(to)?;
+        self.adjacency_list[u].iter().find(|e| e.0 == v).map(|e| &e.1)
+    }
+
+    pub fn neighbors(&self, node: &N) -> Option<Vec<(&N, &E)>> {
+        self.index.get(node).map(|i| {
+            self.adjacency_list[*i]
+                .iter()
+                .map(|(n, e)| (&self.nodes[*n], e))
+                .collect()
+        })
+    }
+
+    fn dijkstra<W: Weight>(
+        &self,
+        src: usize,
+        dest: Option<usize>,
+        weight: impl Fn(&E) -> W,
+    ) -> (Vec<Option<W>>, Vec<Option<usize>>) {
+        dijkstra(
+            self.nodes.len(),
+            src,
+            dest,
+            |u| self.adjacency_list[u].iter().map(|(v, e)| (*v, e)),
+            |_, e| Some(weight(e)),
+        )
+    }
+
+    fn build_path(&self, parent: &[Option<usize>], dest: usize) -> Vec<N> {
+        let mut path = vec![self.nodes[dest].clone()];
+        let mut p = parent[dest];
+        while let Some(n) = p {
+            path.push(self.nodes[n].clone());
+            p = parent[n];
+        }
+        path.reverse();
+        path
+    }
+
+    /// Dijkstra's algorithm; `weight` must not return negative values.
+    pub fn shortest_path<W: Weight>(
+        &self,
+        from: &N,
+        to: &N,
+        weight: impl Fn(&E) -> W,
+    ) -> Option<(W, Vec<N>)> {
+        let src = *self.index.get(from)?;
+        let dest = *self.index.get(to)?;
+
+        let (distance, parent) = self.dijkstra(src, Some(dest), weight);
+        distance[dest].map(|cost| (cost, self.build_path(&parent, dest)))
+    }
+
+    pub fn shortest_paths_from<W: Weight>(
+        &self,
+        from: &N,
+        weight: impl Fn(&E) -> W,
+    ) -> Option<HashMap<N, (W, Vec<N>)>> {
+        let src = *self.index.get(from)?;
+
+        let (distance, parent) = self.dijkstra(src, None, weight);
+        Some(
+            distance
+                .into_iter()
+                .enumerate()
+                .filter_map(|(i, d)| {
+                    d.map(|cost| (self.nodes[i].clone(), (cost, self.build_path(&parent, i))))
+                })
+                .collect(),
+        )
+    }
+
+    /// Kruskal's algorithm, treating edges as undirected. Returns a minimum
+    /// spanning forest if the graph is not connected.
+    pub fn minimum_spanning_tree<W: Weight>(
+        &self,
+        weight: impl Fn(&E) -> W,
+    ) -> (W, Vec<(N, N, W)>) {
+        let edges = self
+            .adjacency_list
+            .iter()
+            .enumerate()
+            .flat_map(|(u, edges)| edges.iter().map(move |e| (u, e)))
+            .map(|(u, (v, payload))| (weight(payload), u, *v))
+            .collect();
+
+        let (total, tree) = kruskal(self.nodes.len(), edges);
+        let tree = tree
+            .into_iter()
+            .map(|(u, v, w)| (self.nodes[u].clone(), self.nodes[v].clone(), w))
+            .collect();
+        (total, tree)
+    }
+}
@@ -1,13 +1,163 @@
-use std::cmp::{Ord, Ordering, min};
-use std::collections::{BTreeSet, BinaryHeap, HashMap, HashSet};
+use crate::generic_graph;
+use std::cmp::{min, Ord, Ordering, Reverse};
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet, VecDeque};
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
 use std::iter::FromIterator;
+use std::mem;
 
 type KeyType = u64;
+type WeightType = i32;
+type Paths<T> = HashMap<KeyType, (T, Vec<KeyType>)>;
+type SpanningTree = (i64, Vec<(KeyType, KeyType, WeightType)>);
+// distance and parent per node
+type Distances = (Vec<Option<i64>>, Vec<Option<usize>>);
+
+/// The nodes of a cycle, in edge order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cycle(pub Vec<KeyType>);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MaximumFlow {
+    pub value: i64,
+    /// `(from, to, flow)` for every edge that carries flow
+    pub flows: Vec<(KeyType, KeyType, i64)>,
+    /// saturated edges separating the source side from the sink side
+    pub min_cut: Vec<(KeyType, KeyType)>,
+}
+
+// arcs come in pairs: an edge at `2k` and its residual arc at `2k + 1`
+struct FlowNetwork {
+    to: Vec<usize>,
+    capacity: Vec<i64>,
+    flow: Vec<i64>,
+    adjacency: Vec<Vec<usize>>,
+}
+
+impl FlowNetwork {
+    fn residual(&self, arc: usize) -> i64 {
+        self.capacity[arc] - self.flow[arc]
+    }
+
+    fn push(&mut self, arc: usize, amount: i64) {
+        self.flow[arc] += amount;
+        self.flow[arc ^ 1] -= amount;
+    }
+
+    fn from(&self, arc: usize) -> usize {
+        self.to[arc ^ 1]
+    }
+
+    fn bfs(&self, src: usize) -> Vec<Option<usize>> {
+        let mut level = vec![None; self.adjacency.len()];
+        level[src] = Some(0);
+        let mut queue = VecDeque::from(vec![src]);
+        while let Some(u) = queue.pop_front() {
+            for &a in &self.adjacency[u] {
+                let v = self.to[a];
+                if level[v].is_none() && self.residual(a) > 0 {
+                    level[v] = level[u].map(|l| l + 1);
+                    queue.push_back(v);
+                }
+            }
+        }
+        level
+    }
+
+    fn augment(&mut self, path: &[usize]) -> i64 {
+        let bottleneck = path
+            .iter()
+            .map(|a| self.residual(*a))
+            .min()
+            .expect("Augmenting paths are not empty");
+        for a in path {
+            self.push(*a, bottleneck);
+        }
+        bottleneck
+    }
+
+    fn edmonds_karp(&mut self, src: usize, sink: usize) -> i64 {
+        let mut total = 0;
+        loop {
+            let mut via = vec![None; self.adjacency.len()];
+            let mut queue = VecDeque::from(vec![src]);
+            while let Some(u) = queue.pop_front() {
+                if u == sink {
+                    break;
+                }
+                for &a in &self.adjacency[u] {
+                    let v = self.to[a];
+                    if v != src && via[v].is_none() && self.residual(a) > 0 {
+                        via[v] = Some(a);
+                        queue.push_back(v);
+                    }
+                }
+            }
+            if via[sink].is_none() {
+                return total;
+            }
+
+            let mut path = vec![];
+            let mut v = sink;
+            while let Some(a) = via[v] {
+                path.push(a);
+                v = self.from(a);
+            }
+            total += self.augment(&path);
+        }
+    }
+
+    fn dinic(&mut self, src: usize, sink: usize) -> i64 {
+        let mut total = 0;
+        loop {
+            let mut level = self.bfs(src);
+            if level[sink].is_none() {
+                return total;
+            }
+            let mut next = vec![0; self.adjacency.len()];
+            let mut path = vec![];
+            let mut u = src;
+            // find blocking flow along the level graph
+            loop {
+                if u == sink {
+                    total += self.augment(&path);
+                    path.clear();
+                    u = src;
+                    continue;
+                }
+                let advance = self.adjacency[u][next[u]..].iter().position(|&a| {
+                    let v = self.to[a];
+                    self.residual(a) > 0 && level[v] == level[u].map(|l| l + 1)
+                });
+                match advance {
+                    Some(offset) => {
+                        next[u] += offset;
+                        let a = self.adjacency[u][next[u]];
+                        path.push(a);
+                        u = self.to[a];
+                    }
+                    None => {
+                        next[u] = self.adjacency[u].len();
+                        level[u] = None;
+                        match path.pop() {
+                            Some(a) => {
+                                u = self.from(a);
+                                next[u] += 1;
+                            }
+                            None => break,
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
 
 #[derive(Eq, PartialEq, Clone, Debug)]
 enum TentativeWeight {
     Infinite,
-    Number(u32),
+    Number(u64),
 }
 
 impl Ord for TentativeWeight {
@@ -32,26 +182,265 @@ impl PartialOrd for TentativeWeight {
 }
 
 #[derive(Clone, Debug)]
-struct Edge {
-    weight: u32,
-    node: usize,
+pub struct Edge {
+    pub(crate) weight: WeightType,
+    pub(crate) node: usize,
 }
 
-fn min_index(weights: &Vec<TentativeWeight>, nodes: &Vec<usize>) -> usize {
-    let mut min_weight = (weights[0].clone(), 0);
-    for node in nodes.iter() {
-        if let Some(n) = weights.get(*node) {
-            if n < &min_weight.0 {
-            min_weight = ((&weights[*node]).clone(), node.clone())}
+impl Edge {
+    pub fn weight(&self) -> WeightType {
+        self.weight
+    }
+
+    /// The index of the node this edge points to.
+    pub fn target(&self) -> usize {
+        self.node
+    }
+}
+
+/// Read access to a graph's structure, shared by all representations.
+pub trait GraphAccess {
+    fn node_count(&self) -> usize;
+    fn node_key(&self, index: usize) -> KeyType;
+    fn node_index(&self, node: KeyType) -> Option<usize>;
+    fn out_edges(&self, index: usize) -> &[Edge];
+    fn has_negative_weights(&self) -> bool;
+
+    /// Dijkstra's algorithm, or Bellman-Ford's if the graph has negative
+    /// weights. `None` if `to` can't be reached, or if a negative cycle can
+    /// be reached from `from`.
+    fn shortest_path(&self, from: KeyType, to: KeyType) -> Option<(i64, Vec<KeyType>)> {
+        let src = self.node_index(from)?;
+        let dest = self.node_index(to)?;
+
+        let (distance, parent) = shortest_distances(self, src, Some(dest))?;
+        distance[dest].map(|cost| (cost, build_path(self, &parent, dest)))
+    }
+
+    /// Like `shortest_path`, leaving out the nodes it has no cost for.
+    fn shortest_paths_from(&self, from: KeyType) -> Option<Paths<i64>> {
+        let src = self.node_index(from)?;
+
+        let (distance, parent) = shortest_distances(self, src, None)?;
+        Some(
+            distance
+                .into_iter()
+                .enumerate()
+                .filter_map(|(i, d)| {
+                    d.map(|cost| (self.node_key(i), (cost, build_path(self, &parent, i))))
+                })
+                .collect(),
+        )
+    }
+
+    /// All nodes reachable in `1..=degree` hops.
+    fn connected(&self, from: KeyType, degree: usize) -> Option<HashSet<KeyType>> {
+        self.node_index(from).map(|i| {
+            let mut visited = vec![false; self.node_count()];
+            let mut frontier = vec![i];
+            for _ in 0..degree {
+                let mut next = vec![];
+                for u in frontier {
+                    for e in self.out_edges(u) {
+                        if !visited[e.node] {
+                            visited[e.node] = true;
+                            next.push(e.node);
+                        }
+                    }
+                }
+                frontier = next;
+            }
+            visited
+                .into_iter()
+                .enumerate()
+                .filter(|(_, v)| *v)
+                .map(|(n, _)| self.node_key(n))
+                .collect()
+        })
+    }
+}
+
+fn dijkstra<G: GraphAccess + ?Sized>(
+    graph: &G,
+    src: usize,
+    dest: Option<usize>,
+    weight: impl Fn(usize, &Edge) -> Option<u64>,
+) -> (Vec<Option<u64>>, Vec<Option<usize>>) {
+    generic_graph::dijkstra(
+        graph.node_count(),
+        src,
+        dest,
+        |u| graph.out_edges(u).iter().map(|e| (e.node, e)),
+        weight,
+    )
+}
+
+/// Distances from `src`, using Bellman-Ford if there are negative weights.
+/// `None` if a negative cycle can be reached.
+fn shortest_distances<G: GraphAccess + ?Sized>(
+    graph: &G,
+    src: usize,
+    dest: Option<usize>,
+) -> Option<Distances> {
+    if graph.has_negative_weights() {
+        let mut distance = vec![None; graph.node_count()];
+        distance[src] = Some(0);
+        let mut parent = vec![None; graph.node_count()];
+        relax_all(graph, &mut distance, &mut parent).ok()?;
+        Some((distance, parent))
+    } else {
+        let (distance, parent) = dijkstra(graph, src, dest, |_, e| Some(e.weight as u64));
+        let distance = distance.into_iter().map(|d| d.map(|cost| cost as i64)).collect();
+        Some((distance, parent))
+    }
+}
+
+/// Bellman-Ford's rounds over all edges, starting from the nodes that have a
+/// distance. Fails with a negative cycle reachable from them.
+fn relax_all<G: GraphAccess + ?Sized>(
+    graph: &G,
+    distance: &mut [Option<i64>],
+    parent: &mut [Option<usize>],
+) -> Result<(), Cycle> {
+    for round in 0..=graph.node_count() {
+        let mut last_relaxed = None;
+        for u in 0..graph.node_count() {
+            if let Some(dist) = distance[u] {
+                for e in graph.out_edges(u) {
+                    let new_distance = dist + e.weight as i64;
+                    let shorter = match distance[e.node] {
+                        Some(d) => new_distance < d,
+                        None => true,
+                    };
+                    if shorter {
+                        distance[e.node] = Some(new_distance);
+                        parent[e.node] = Some(u);
+                        last_relaxed = Some(e.node);
+                    }
+                }
+            }
+        }
+        match last_relaxed {
+            None => return Ok(()),
+            Some(n) if round == graph.node_count() => return Err(find_cycle(graph, parent, n)),
+            _ => {}
         }
     }
-    return min_weight.1;
+    Ok(())
 }
 
+fn find_cycle<G: GraphAccess + ?Sized>(graph: &G, parent: &[Option<usize>], start: usize) -> Cycle {
+    // walking back far enough always ends up on the cycle
+    let mut on_cycle = start;
+    for _ in 0..graph.node_count() {
+        on_cycle = parent[on_cycle].expect("Relaxed nodes have a parent");
+    }
+    let mut cycle = vec![graph.node_key(on_cycle)];
+    let mut n = parent[on_cycle].expect("Relaxed nodes have a parent");
+    while n != on_cycle {
+        cycle.push(graph.node_key(n));
+        n = parent[n].expect("Relaxed nodes have a parent");
+    }
+    cycle.reverse();
+    Cycle(cycle)
+}
+
+fn trace(parent: &[Option<usize>], dest: usize) -> Vec<usize> {
+    let mut path = vec![dest];
+    let mut p = parent[dest];
+    while let Some(n) = p {
+        path.push(n);
+        p = parent[n];
+    }
+    path.reverse();
+    path
+}
+
+fn build_path<G: GraphAccess + ?Sized>(
+    graph: &G,
+    parent: &[Option<usize>],
+    dest: usize,
+) -> Vec<KeyType> {
+    trace(parent, dest)
+        .into_iter()
+        .map(|n| graph.node_key(n))
+        .collect()
+}
+
+pub(crate) struct DisjointSet {
+    parent: Vec<usize>,
+    rank: Vec<usize>,
+}
+
+impl DisjointSet {
+    pub fn new(size: usize) -> DisjointSet {
+        DisjointSet {
+            parent: (0..size).collect(),
+            rank: vec![0; size],
+        }
+    }
+
+    pub fn find(&mut self, i: usize) -> usize {
+        let mut root = i;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        // path compression
+        let mut i = i;
+        while self.parent[i] != root {
+            i = mem::replace(&mut self.parent[i], root);
+        }
+        root
+    }
+
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+        match self.rank[a].cmp(&self.rank[b]) {
+            Ordering::Less => self.parent[a] = b,
+            Ordering::Greater => self.parent[b] = a,
+            Ordering::Equal => {
+                self.parent[b] = a;
+                self.rank[a] += 1;
+            }
+        }
+        true
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum GraphError<N = KeyType> {
+    UnknownNode(N),
+    DuplicateNode(N),
+    UnknownEdge(N, N),
+    DuplicateEdge(N, N),
+}
+
+impl<N: fmt::Display> fmt::Display for GraphError<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphError::UnknownNode(n) => write!(f, "unknown node {}", n),
+            GraphError::DuplicateNode(n) => write!(f, "duplicate node {}", n),
+            GraphError::UnknownEdge(a, b) => write!(f, "no edge from {} to {}", a, b),
+            GraphError::DuplicateEdge(a, b) => write!(f, "duplicate edge from {} to {}", a, b),
+        }
+    }
+}
+
+impl<N: fmt::Debug + fmt::Display> Error for GraphError<N> {}
+
+fn count_negative(edges: &[Edge]) -> usize {
+    edges.iter().filter(|e| e.weight < 0).count()
+}
 
 pub struct InternetOfThings {
     adjacency_list: Vec<Vec<Edge>>,
     nodes: Vec<KeyType>,
+    index: HashMap<KeyType, usize>,
+    negative_edges: usize,
+    directed: bool,
 }
 
 impl InternetOfThings {
@@ -59,15 +448,42 @@ impl InternetOfThings {
         InternetOfThings {
             adjacency_list: vec![],
             nodes: vec![],
+            index: HashMap::new(),
+            negative_edges: 0,
+            directed: true,
+        }
+    }
+
+    /// A graph where every edge can be traversed both ways.
+    pub fn new_undirected() -> InternetOfThings {
+        InternetOfThings {
+            directed: false,
+            ..InternetOfThings::new()
         }
     }
 
+    pub fn is_directed(&self) -> bool {
+        self.directed
+    }
+
     fn get_node_index(&self, node: KeyType) -> Option<usize> {
-        self.nodes.iter().position(|n| n == &node)
+        self.index.get(&node).cloned()
     }
 
+    fn require_node_index(&self, node: KeyType) -> Result<usize, GraphError> {
+        self.get_node_index(node).ok_or(GraphError::UnknownNode(node))
+    }
+
+    /// Counts every undirected edge once.
     pub fn edges(&self) -> u64 {
-        self.adjacency_list.iter().fold(0u64, |p, c| p + c.len() as u64)
+        if self.directed {
+            self.adjacency_list.iter().fold(0u64, |p, c| p + c.len() as u64)
+        } else {
+            self.adjacency_list
+                .iter()
+                .enumerate()
+                .fold(0u64, |p, (u, c)| p + c.iter().filter(|e| e.node >= u).count() as u64)
+        }
     }
 
     pub fn nodes(&self) -> usize {
@@ -75,117 +491,905 @@ impl InternetOfThings {
     }
 
     pub fn set_nodes(&mut self, nodes: Vec<KeyType>) {
+        self.index = nodes.iter().enumerate().map(|(i, n)| (*n, i)).collect();
         self.nodes = nodes;
-        self.adjacency_list = vec![vec![]; self.nodes.len()]
+        self.adjacency_list = vec![vec![]; self.nodes.len()];
+        self.negative_edges = 0;
     }
 
-    pub fn set_edges(&mut self, from: KeyType, edges: Vec<(u32, KeyType)>) {
-        let edges: Vec<Edge> = edges.into_iter().filter_map(|e| {
-            if let Some(to) = self.get_node_index(e.1) {
-                Some(Edge { weight: e.0, node: to }) 
-                } else {
-                    None
-                }}).collect();
-        match self.nodes.iter().position(|n| n == &from) {
-            Some(i) => self.adjacency_list[i] = edges,
+    /// Replaces all edges of `from`, adding the node if it's new. Unlike
+    /// `add_edge`, it keeps parallel edges.
+    pub fn set_edges(
+        &mut self,
+        from: KeyType,
+        edges: Vec<(WeightType, KeyType)>,
+    ) -> Result<(), GraphError> {
+        if let Some(e) = edges
+            .iter()
+            .find(|e| e.1 != from && self.get_node_index(e.1).is_none())
+        {
+            return Err(GraphError::UnknownNode(e.1));
+        }
+        let u = match self.get_node_index(from) {
+            Some(u) => u,
             None => {
-                self.nodes.push(from);
-                self.adjacency_list.push(edges)
+                self.add_node(from)?;
+                self.nodes.len() - 1
+            }
+        };
+        let edges: Vec<Edge> = edges
+            .into_iter()
+            .map(|e| Edge {
+                weight: e.0,
+                node: self.index[&e.1],
+            })
+            .collect();
+
+        let replaced = mem::take(&mut self.adjacency_list[u]);
+        self.negative_edges -= count_negative(&replaced);
+        if !self.directed {
+            for e in replaced.iter().filter(|e| e.node != u) {
+                self.remove_edge_at(e.node, u);
             }
         }
+        for e in edges {
+            self.insert_edge_at(u, e.node, e.weight);
+        }
+        Ok(())
     }
 
-    pub fn shortest_path(&self, from: KeyType, to: KeyType) -> Option<(u32, Vec<KeyType>)> {
-        let mut src = None;
-        let mut dest = None;
+    pub fn add_node(&mut self, node: KeyType) -> Result<(), GraphError> {
+        if self.index.contains_key(&node) {
+            return Err(GraphError::DuplicateNode(node));
+        }
+        self.index.insert(node, self.nodes.len());
+        self.nodes.push(node);
+        self.adjacency_list.push(vec![]);
+        Ok(())
+    }
 
-        for (i, n) in self.nodes.iter().enumerate() {
-            if n == &from {
-                src = Some(i);
+    /// Removes a node and all edges from and to it.
+    pub fn remove_node(&mut self, node: KeyType) -> Result<(), GraphError> {
+        let i = self.require_node_index(node)?;
+        for edges in self.adjacency_list.iter_mut() {
+            self.negative_edges -= count_negative(edges);
+            edges.retain(|e| e.node != i);
+            self.negative_edges += count_negative(edges);
+        }
+        self.negative_edges -= count_negative(&self.adjacency_list[i]);
+
+        // the last node moves into the free slot
+        let last = self.nodes.len() - 1;
+        self.nodes.swap_remove(i);
+        self.adjacency_list.swap_remove(i);
+        self.index.remove(&node);
+        if i != last {
+            self.index.insert(self.nodes[i], i);
+            for e in self.adjacency_list.iter_mut().flatten() {
+                if e.node == last {
+                    e.node = i;
+                }
             }
-            if n == &to {
-                dest = Some(i);
+        }
+        Ok(())
+    }
+
+    fn insert_edge_at(&mut self, from: usize, to: usize, weight: WeightType) {
+        let negative = if weight < 0 { 1 } else { 0 };
+        self.adjacency_list[from].push(Edge { weight, node: to });
+        self.negative_edges += negative;
+        if !self.directed && from != to {
+            self.adjacency_list[to].push(Edge { weight, node: from });
+            self.negative_edges += negative;
+        }
+    }
+
+    fn remove_edge_at(&mut self, from: usize, to: usize) -> bool {
+        let before = self.adjacency_list[from].len();
+        self.negative_edges -= count_negative(&self.adjacency_list[from]);
+        self.adjacency_list[from].retain(|e| e.node != to);
+        self.negative_edges += count_negative(&self.adjacency_list[from]);
+        before != self.adjacency_list[from].len()
+    }
+
+    pub fn add_edge(
+        &mut self,
+        from: KeyType,
+        to: KeyType,
+        weight: WeightType,
+    ) -> Result<(), GraphError> {
+        let u = self.require_node_index(from)?;
+        let v = self.require_node_index(to)?;
+        if self.adjacency_list[u].iter().any(|e| e.node == v) {
+            return Err(GraphError::DuplicateEdge(from, to));
+        }
+        self.insert_edge_at(u, v, weight);
+        Ok(())
+    }
+
+    /// Like `add_edge`, but keeps parallel edges the way `set_edges` does.
+    pub(crate) fn insert_edge(
+        &mut self,
+        from: KeyType,
+        to: KeyType,
+        weight: WeightType,
+    ) -> Result<(), GraphError> {
+        let u = self.require_node_index(from)?;
+        let v = self.require_node_index(to)?;
+        self.insert_edge_at(u, v, weight);
+        Ok(())
+    }
+
+    pub fn remove_edge(&mut self, from: KeyType, to: KeyType) -> Result<(), GraphError> {
+        let u = self.require_node_index(from)?;
+        let v = self.require_node_index(to)?;
+        if !self.remove_edge_at(u, v) {
+            return Err(GraphError::UnknownEdge(from, to));
+        }
+        if !self.directed {
+            self.remove_edge_at(v, u);
+        }
+        Ok(())
+    }
+
+    pub fn update_weight(
+        &mut self,
+        from: KeyType,
+        to: KeyType,
+        weight: WeightType,
+    ) -> Result<(), GraphError> {
+        let u = self.require_node_index(from)?;
+        let v = self.require_node_index(to)?;
+        let mut pairs = vec![(u, v)];
+        if !self.directed {
+            pairs.push((v, u));
+        }
+        for (a, b) in pairs {
+            let edges = &mut self.adjacency_list[a];
+            if !edges.iter().any(|e| e.node == b) {
+                return Err(GraphError::UnknownEdge(from, to));
             }
-            if src.is_some() && dest.is_some() {
-                break;
+            self.negative_edges -= count_negative(edges);
+            for e in edges.iter_mut().filter(|e| e.node == b) {
+                e.weight = weight;
             }
+            self.negative_edges += count_negative(edges);
         }
-        if src.is_some() && dest.is_some() {
-            let (src, dest) = (src.unwrap(), dest.unwrap());
+        Ok(())
+    }
 
-            let mut distance: Vec<TentativeWeight> =
-                vec![TentativeWeight::Infinite; self.nodes.len()];
-            distance[src] = TentativeWeight::Number(0);
+    /// `(node, weight)` for all outgoing edges of `node`.
+    pub fn neighbors(&self, node: KeyType) -> Option<Vec<(KeyType, WeightType)>> {
+        self.get_node_index(node).map(|i| {
+            self.adjacency_list[i]
+                .iter()
+                .map(|e| (self.nodes[e.node], e.weight))
+                .collect()
+        })
+    }
 
-            let mut open: Vec<usize> = (0..self.nodes.len()).into_iter().collect();
-            let mut parent = vec![None; self.nodes.len()];
-            let mut found = false;
-            while !open.is_empty() {
-                let u = min_index(&distance, &open);
-                let u = open.remove(u);
+    pub fn has_negative_weights(&self) -> bool {
+        self.negative_edges > 0
+    }
 
-                if u == dest {
-                    found = true;
-                    break;
+    /// Dijkstra's algorithm, or Bellman-Ford's if the graph has negative
+    /// weights. `None` if `to` can't be reached, or if a negative cycle can
+    /// be reached from `from`.
+    pub fn shortest_path(&self, from: KeyType, to: KeyType) -> Option<(i64, Vec<KeyType>)> {
+        GraphAccess::shortest_path(self, from, to)
+    }
+
+    pub fn shortest_paths_from(&self, from: KeyType) -> Option<Paths<i64>> {
+        GraphAccess::shortest_paths_from(self, from)
+    }
+
+    /// All nodes reachable in `1..=degree` hops.
+    pub fn connected(&self, from: KeyType, degree: usize) -> Option<HashSet<KeyType>> {
+        GraphAccess::connected(self, from, degree)
+    }
+
+    /// Falls back to `shortest_path` if the graph has negative weights.
+    pub fn a_star(
+        &self,
+        from: KeyType,
+        to: KeyType,
+        heuristic: impl Fn(KeyType, KeyType) -> u32,
+    ) -> Option<(i64, Vec<KeyType>)> {
+        let src = self.get_node_index(from)?;
+        let dest = self.get_node_index(to)?;
+        if self.has_negative_weights() {
+            // no heuristic can guide a search that has to revisit nodes
+            return self.shortest_path(from, to);
+        }
+
+        let mut distance: Vec<TentativeWeight> =
+            vec![TentativeWeight::Infinite; self.nodes.len()];
+        distance[src] = TentativeWeight::Number(0);
+
+        let mut open = BinaryHeap::new();
+        open.push(Reverse((heuristic(from, to) as u64, 0, src)));
+        let mut parent = vec![None; self.nodes.len()];
+
+        while let Some(Reverse((_, dist, u))) = open.pop() {
+            // skip entries that were superseded by a shorter route
+            if TentativeWeight::Number(dist) > distance[u] {
+                continue;
+            }
+            if u == dest {
+                return Some((dist as i64, build_path(self, &parent, dest)));
+            }
+
+            for e in &self.adjacency_list[u] {
+                let new_distance = dist.saturating_add(e.weight as u64);
+
+                if TentativeWeight::Number(new_distance) < distance[e.node] {
+                    distance[e.node] = TentativeWeight::Number(new_distance);
+                    parent[e.node] = Some(u);
+                    let estimate =
+                        new_distance.saturating_add(heuristic(self.nodes[e.node], to) as u64);
+                    open.push(Reverse((estimate, new_distance, e.node)));
                 }
+            }
+        }
+        None
+    }
 
-                let dist = distance[u].clone();
+    /// Shortest paths to all reachable nodes, or a negative cycle reachable from `from`.
+    pub fn bellman_ford(&self, from: KeyType) -> Option<Result<Paths<i64>, Cycle>> {
+        let src = self.get_node_index(from)?;
 
-                for e in &self.adjacency_list[u] {
-                    let new_distance = match dist {
-                        TentativeWeight::Number(n) => TentativeWeight::Number(n + e.weight),
-                        _ => TentativeWeight::Infinite,
-                    };
-                    
-                    let old_distance = distance[e.node].clone();
+        let mut distance = vec![None; self.nodes.len()];
+        distance[src] = Some(0);
+        let mut parent = vec![None; self.nodes.len()];
 
-                    if new_distance < old_distance {
-                        distance[e.node] = new_distance;
-                        parent[e.node] = Some(u);
+        Some(relax_all(self, &mut distance, &mut parent).map(|_| {
+            distance
+                .into_iter()
+                .enumerate()
+                .filter_map(|(i, d)| {
+                    d.map(|cost| (self.nodes[i], (cost, build_path(self, &parent, i))))
+                })
+                .collect()
+        }))
+    }
+
+    /// All-pairs shortest paths, or any negative cycle in the graph.
+    pub fn johnson(&self) -> Result<HashMap<KeyType, Paths<i64>>, Cycle> {
+        // a virtual node with zero-weight edges to all others yields the potentials
+        let mut potential = vec![Some(0); self.nodes.len()];
+        let mut parent = vec![None; self.nodes.len()];
+        relax_all(self, &mut potential, &mut parent)?;
+        let h: Vec<i64> = potential.into_iter().map(|p| p.unwrap_or(0)).collect();
+
+        Ok((0..self.nodes.len())
+            .map(|src| {
+                let (distance, parent) = dijkstra(self, src, None, |u, e| {
+                    let reweighted = e.weight as i64 + h[u] - h[e.node];
+                    Some(u64::try_from(reweighted).expect("Potentials keep weights non-negative"))
+                });
+                let paths = distance
+                    .into_iter()
+                    .enumerate()
+                    .filter_map(|(i, d)| {
+                        d.map(|cost| {
+                            let cost = i64::try_from(cost)
+                                .ok()
+                                .and_then(|c| c.checked_sub(h[src]))
+                                .and_then(|c| c.checked_add(h[i]))
+                                .expect("Shortest paths fit into i64");
+                            (self.nodes[i], (cost, build_path(self, &parent, i)))
+                        })
+                    })
+                    .collect();
+                (self.nodes[src], paths)
+            })
+            .collect())
+    }
+
+    /// Kruskal's algorithm, treating edges as undirected. Returns a minimum
+    /// spanning forest if the graph is not connected.
+    pub fn minimum_spanning_tree(&self) -> SpanningTree {
+        let edges = self
+            .adjacency_list
+            .iter()
+            .enumerate()
+            .flat_map(|(u, edges)| edges.iter().map(move |e| (e.weight as i64, u, e.node)))
+            .collect();
+
+        let (total, tree) = generic_graph::kruskal(self.nodes.len(), edges);
+        let tree = tree
+            .into_iter()
+            .map(|(u, v, w)| (self.nodes[u], self.nodes[v], w as WeightType))
+            .collect();
+        (total, tree)
+    }
+
+    /// Prim's algorithm, treating edges as undirected. Returns a minimum
+    /// spanning forest if the graph is not connected.
+    pub fn minimum_spanning_tree_prim(&self) -> SpanningTree {
+        let mut undirected = vec![vec![]; self.nodes.len()];
+        for (u, edges) in self.adjacency_list.iter().enumerate() {
+            for e in edges {
+                undirected[u].push((e.weight, e.node));
+                undirected[e.node].push((e.weight, u));
+            }
+        }
+
+        let mut visited = vec![false; self.nodes.len()];
+        let mut total = 0;
+        let mut tree = vec![];
+        for start in 0..self.nodes.len() {
+            if visited[start] {
+                continue;
+            }
+            visited[start] = true;
+            let mut open: BinaryHeap<_> = undirected[start]
+                .iter()
+                .map(|(w, v)| Reverse((*w, start, *v)))
+                .collect();
+
+            while let Some(Reverse((weight, u, v))) = open.pop() {
+                if visited[v] {
+                    continue;
+                }
+                visited[v] = true;
+                total += weight as i64;
+                tree.push((self.nodes[u], self.nodes[v], weight));
+                open.extend(
+                    undirected[v]
+                        .iter()
+                        .filter(|(_, n)| !visited[*n])
+                        .map(|(w, n)| Reverse((*w, v, *n))),
+                );
+            }
+        }
+        (total, tree)
+    }
+
+    fn flow_network(&self, capacity: impl Fn(&Edge) -> i64) -> FlowNetwork {
+        let mut network = FlowNetwork {
+            to: vec![],
+            capacity: vec![],
+            flow: vec![],
+            adjacency: vec![vec![]; self.nodes.len()],
+        };
+        for (u, edges) in self.adjacency_list.iter().enumerate() {
+            for e in edges {
+                network.adjacency[u].push(network.to.len());
+                network.to.push(e.node);
+                network.capacity.push(capacity(e));
+                network.adjacency[e.node].push(network.to.len());
+                network.to.push(u);
+                network.capacity.push(0);
+            }
+        }
+        network.flow = vec![0; network.to.len()];
+        network
+    }
+
+    fn maximum_flow_with(
+        &self,
+        source: KeyType,
+        sink: KeyType,
+        algorithm: impl Fn(&mut FlowNetwork, usize, usize) -> i64,
+    ) -> Option<MaximumFlow> {
+        let src = self.get_node_index(source)?;
+        let dest = self.get_node_index(sink)?;
+        if src == dest || self.has_negative_weights() {
+            return None;
+        }
+
+        let mut network = self.flow_network(|e| e.weight as i64);
+        let value = algorithm(&mut network, src, dest);
+
+        let source_side = network.bfs(src);
+        let mut flows = vec![];
+        let mut min_cut = vec![];
+        for a in (0..network.to.len()).step_by(2) {
+            let (u, v) = (network.from(a), network.to[a]);
+            if network.flow[a] > 0 {
+                flows.push((self.nodes[u], self.nodes[v], network.flow[a]));
+            }
+            if network.capacity[a] > 0 && source_side[u].is_some() && source_side[v].is_none() {
+                min_cut.push((self.nodes[u], self.nodes[v]));
+            }
+        }
+        Some(MaximumFlow {
+            value,
+            flows,
+            min_cut,
+        })
+    }
+
+    /// Edmonds-Karp, using edge weights as capacities.
+    pub fn maximum_flow(&self, source: KeyType, sink: KeyType) -> Option<MaximumFlow> {
+        self.maximum_flow_with(source, sink, FlowNetwork::edmonds_karp)
+    }
+
+    /// Dinic's algorithm, using edge weights as capacities.
+    pub fn maximum_flow_dinic(&self, source: KeyType, sink: KeyType) -> Option<MaximumFlow> {
+        self.maximum_flow_with(source, sink, FlowNetwork::dinic)
+    }
+
+    fn edge_weight(&self, from: usize, to: usize) -> WeightType {
+        self.adjacency_list[from]
+            .iter()
+            .filter(|e| e.node == to)
+            .map(|e| e.weight)
+            .min()
+            .expect("Paths only use existing edges")
+    }
+
+    /// Yen's algorithm: up to `k` loopless paths in order of their cost.
+    /// Empty for unknown nodes or if the graph has negative weights.
+    pub fn k_shortest_paths(
+        &self,
+        from: KeyType,
+        to: KeyType,
+        k: usize,
+    ) -> Vec<(u64, Vec<KeyType>)> {
+        let (src, dest) = match (self.get_node_index(from), self.get_node_index(to)) {
+            (Some(src), Some(dest)) if !self.has_negative_weights() => (src, dest),
+            _ => return vec![],
+        };
+
+        let mut found: Vec<(u64, Vec<usize>)> = vec![];
+        let mut candidates = BTreeSet::new();
+        let (distance, parent) = dijkstra(self, src, Some(dest), |_, e| Some(e.weight as u64));
+        if let Some(cost) = distance[dest] {
+            candidates.insert((cost, trace(&parent, dest)));
+        }
+
+        while found.len() < k {
+            let best = match candidates.iter().next().cloned() {
+                Some(best) => best,
+                None => break,
+            };
+            candidates.remove(&best);
+            let previous = &best.1;
+
+            // branch off at every node of the previous path
+            let mut root_cost = 0;
+            let mut on_root = vec![false; self.nodes.len()];
+            for i in 0..previous.len() - 1 {
+                let spur = previous[i];
+                let root = &previous[..=i];
+                let removed: HashSet<(usize, usize)> = found
+                    .iter()
+                    .chain(Some(&best))
+                    .filter(|(_, p)| p.len() > i + 1 && &p[..=i] == root)
+                    .map(|(_, p)| (p[i], p[i + 1]))
+                    .collect();
+
+                let (distance, parent) = dijkstra(self, spur, Some(dest), |u, e| {
+                    if on_root[e.node] || removed.contains(&(u, e.node)) {
+                        None
+                    } else {
+                        Some(e.weight as u64)
                     }
+                });
+                if let Some(cost) = distance[dest] {
+                    let mut path = root[..i].to_vec();
+                    path.extend(trace(&parent, dest));
+                    candidates.insert((root_cost + cost, path));
                 }
+
+                on_root[spur] = true;
+                root_cost += self.edge_weight(spur, previous[i + 1]) as u64;
             }
-            if found {
-                let mut path = vec![];
-                let mut p = parent[dest].unwrap();
-                path.push(self.nodes[dest].clone());
-                while p != src {
-                    path.push(self.nodes[p].clone());
-                    p = parent[p].unwrap();
+            found.push(best);
+        }
+
+        found
+            .into_iter()
+            .map(|(cost, path)| (cost, path.into_iter().map(|n| self.nodes[n]).collect()))
+            .collect()
+    }
+
+    /// A maximum set of paths that share no edge, cheapest first and those
+    /// with fewer hops first among equally cheap ones.
+    pub fn disjoint_paths(&self, from: KeyType, to: KeyType) -> Vec<Vec<KeyType>> {
+        let (src, dest) = match (self.get_node_index(from), self.get_node_index(to)) {
+            (Some(src), Some(dest)) if src != dest => (src, dest),
+            _ => return vec![],
+        };
+
+        let mut network = self.flow_network(|_| 1);
+        let count = network.edmonds_karp(src, dest);
+        let arcs = (0..network.to.len()).step_by(2);
+        if !self.directed {
+            // an undirected edge may carry only one of its two opposing flows
+            for a in arcs.clone() {
+                let (u, v) = (network.from(a), network.to[a]);
+                if network.flow[a] > 0 {
+                    let opposing = network.adjacency[v]
+                        .iter()
+                        .find(|&&b| b % 2 == 0 && network.to[b] == u && network.flow[b] > 0)
+                        .cloned();
+                    if let Some(b) = opposing {
+                        network.flow[a] = 0;
+                        network.flow[b] = 0;
+                    }
                 }
-                path.push(self.nodes[src].clone());
+            }
+        }
 
-                path.reverse();
-                let cost = match distance[dest] {
-                    TentativeWeight::Number(n) => n,
-                    _ => 0,
-                };
-                Some((cost, path))
-            } else {
-                None
+        let mut paths = vec![];
+        for _ in 0..count {
+            let mut path = vec![src];
+            while let Some(&u) = path.last().filter(|&&u| u != dest) {
+                let a = *network.adjacency[u]
+                    .iter()
+                    .find(|&&a| a % 2 == 0 && network.flow[a] > 0)
+                    .expect("Flow leaving a node is conserved");
+                network.flow[a] = 0;
+                let v = network.to[a];
+                // drop any circulation picked up on the way
+                match path.iter().position(|&n| n == v) {
+                    Some(pos) => path.truncate(pos + 1),
+                    None => path.push(v),
+                }
             }
+            let cost: i64 = path
+                .windows(2)
+                .map(|w| self.edge_weight(w[0], w[1]) as i64)
+                .sum();
+            let hops = path.len();
+            paths.push((cost, hops, path.into_iter().map(|n| self.nodes[n]).collect::<Vec<_>>()));
+        }
+        paths.sort();
+        paths.into_iter().map(|(_, _, path)| path).collect()
+    }
+
+    fn kahn_layers(&self) -> Result<Vec<Vec<usize>>, Cycle> {
+        let mut in_degree = vec![0; self.nodes.len()];
+        for e in self.adjacency_list.iter().flatten() {
+            in_degree[e.node] += 1;
+        }
+
+        let mut layers = vec![];
+        let mut layer: Vec<usize> = (0..self.nodes.len()).filter(|n| in_degree[*n] == 0).collect();
+        let mut done = 0;
+        while !layer.is_empty() {
+            let mut next = vec![];
+            for u in layer.iter() {
+                for e in &self.adjacency_list[*u] {
+                    in_degree[e.node] -= 1;
+                    if in_degree[e.node] == 0 {
+                        next.push(e.node);
+                    }
+                }
+            }
+            done += layer.len();
+            layers.push(mem::replace(&mut layer, next));
+        }
+
+        if done < self.nodes.len() {
+            // every remaining node has a remaining predecessor, so walking
+            // backwards has to run into a cycle
+            let mut predecessor = vec![None; self.nodes.len()];
+            for (u, edges) in self.adjacency_list.iter().enumerate() {
+                for e in edges.iter().filter(|_| in_degree[u] > 0) {
+                    predecessor[e.node] = Some(u);
+                }
+            }
+            let start = (0..self.nodes.len())
+                .find(|n| in_degree[*n] > 0)
+                .expect("Unfinished nodes have an incoming edge");
+            let mut seen = vec![false; self.nodes.len()];
+            let mut n = start;
+            while !seen[n] {
+                seen[n] = true;
+                n = predecessor[n].expect("Unfinished nodes have a predecessor");
+            }
+            let mut cycle = vec![self.nodes[n]];
+            let mut p = predecessor[n].expect("Unfinished nodes have a predecessor");
+            while p != n {
+                cycle.push(self.nodes[p]);
+                p = predecessor[p].expect("Unfinished nodes have a predecessor");
+            }
+            cycle.reverse();
+            Err(Cycle(cycle))
         } else {
-            None
+            Ok(layers)
         }
     }
 
-    pub fn connected(&self, from: KeyType, degree: usize) -> Option<HashSet<KeyType>> {
-        self.nodes.iter().position(|n| n == &from).map(|i| {
-            self.connected_r(i, degree).into_iter().map(|n| self.nodes[n].clone()).collect()
+    /// An order in which every node comes before all nodes its edges point to.
+    pub fn topological_order(&self) -> Result<Vec<KeyType>, Cycle> {
+        self.kahn_layers()
+            .map(|layers| layers.into_iter().flatten().map(|n| self.nodes[n]).collect())
+    }
+
+    /// Batches of nodes that only depend on nodes in earlier batches.
+    pub fn topological_layers(&self) -> Result<Vec<Vec<KeyType>>, Cycle> {
+        self.kahn_layers().map(|layers| {
+            layers
+                .into_iter()
+                .map(|layer| layer.into_iter().map(|n| self.nodes[n]).collect())
+                .collect()
         })
     }
 
-    fn connected_r(&self, from: usize, degree: usize) -> HashSet<usize> {
-        if degree > 0 {
-            self.adjacency_list[from]
-                .iter()
-                .flat_map(|e| {
-                    let mut set = self.connected_r(e.node, degree - 1);
-                    set.insert(e.node);
-                    set
-                }).collect()
-        } else {
-            HashSet::new()
+    pub fn bfs(&self, from: KeyType) -> BreadthFirst<'_> {
+        let mut visited = vec![false; self.nodes.len()];
+        let queue = self
+            .get_node_index(from)
+            .map(|i| {
+                visited[i] = true;
+                VecDeque::from(vec![i])
+            })
+            .unwrap_or_default();
+        BreadthFirst {
+            graph: self,
+            queue,
+            visited,
+        }
+    }
+
+    pub fn dfs(&self, from: KeyType) -> DepthFirst<'_> {
+        DepthFirst {
+            graph: self,
+            stack: self.get_node_index(from).into_iter().collect(),
+            visited: vec![false; self.nodes.len()],
+        }
+    }
+
+    // `(neighbor, link id)` at both ends of every link but self loops. Edges in
+    // opposite directions form one link, any more are parallel links.
+    fn undirected_neighbors(&self) -> Vec<Vec<(usize, usize)>> {
+        let mut links = BTreeMap::new();
+        for (u, edges) in self.adjacency_list.iter().enumerate() {
+            for e in edges.iter().filter(|e| e.node != u) {
+                let count = links.entry((min(u, e.node), u.max(e.node))).or_insert((0, 0));
+                if u < e.node {
+                    count.0 += 1;
+                } else {
+                    count.1 += 1;
+                }
+            }
+        }
+
+        let mut undirected = vec![vec![]; self.nodes.len()];
+        let mut id = 0;
+        for ((u, v), (forward, backward)) in links {
+            // undirected graphs keep every edge in both directions
+            let parallel = if self.directed { forward.max(backward) } else { forward };
+            for _ in 0..parallel {
+                undirected[u].push((v, id));
+                undirected[v].push((u, id));
+                id += 1;
+            }
+        }
+        undirected
+    }
+
+    /// Connected components, treating edges as undirected.
+    pub fn components(&self) -> Vec<Vec<KeyType>> {
+        let undirected = self.undirected_neighbors();
+        let mut visited = vec![false; self.nodes.len()];
+        let mut components = vec![];
+        for start in 0..self.nodes.len() {
+            if visited[start] {
+                continue;
+            }
+            visited[start] = true;
+            let mut stack = vec![start];
+            let mut component = vec![];
+            while let Some(u) = stack.pop() {
+                component.push(self.nodes[u]);
+                for (v, _) in &undirected[u] {
+                    if !visited[*v] {
+                        visited[*v] = true;
+                        stack.push(*v);
+                    }
+                }
+            }
+            components.push(component);
+        }
+        components
+    }
+
+    /// Tarjan's algorithm; components come out in reverse topological order.
+    pub fn strongly_connected_components(&self) -> Vec<Vec<KeyType>> {
+        let n = self.nodes.len();
+        let mut index = vec![None; n];
+        let mut low = vec![0; n];
+        let mut on_stack = vec![false; n];
+        let mut stack = vec![];
+        let mut components = vec![];
+        let mut counter = 0;
+
+        for root in 0..n {
+            if index[root].is_some() {
+                continue;
+            }
+            // (node, next edge) pairs replace the recursion
+            let mut call_stack = vec![(root, 0)];
+            index[root] = Some(counter);
+            low[root] = counter;
+            counter += 1;
+            stack.push(root);
+            on_stack[root] = true;
+
+            while let Some((u, i)) = call_stack.pop() {
+                if let Some(e) = self.adjacency_list[u].get(i) {
+                    call_stack.push((u, i + 1));
+                    let v = e.node;
+                    match index[v] {
+                        None => {
+                            index[v] = Some(counter);
+                            low[v] = counter;
+                            counter += 1;
+                            stack.push(v);
+                            on_stack[v] = true;
+                            call_stack.push((v, 0));
+                        }
+                        Some(idx) if on_stack[v] => low[u] = min(low[u], idx),
+                        _ => {}
+                    }
+                } else {
+                    if let Some(&(p, _)) = call_stack.last() {
+                        low[p] = min(low[p], low[u]);
+                    }
+                    if Some(low[u]) == index[u] {
+                        let mut component = vec![];
+                        loop {
+                            let v = stack.pop().expect("Component root is on the stack");
+                            on_stack[v] = false;
+                            component.push(self.nodes[v]);
+                            if v == u {
+                                break;
+                            }
+                        }
+                        components.push(component);
+                    }
+                }
+            }
+        }
+        components
+    }
+
+    fn low_links(&self) -> (Vec<(KeyType, KeyType)>, Vec<KeyType>) {
+        let undirected = self.undirected_neighbors();
+        let n = self.nodes.len();
+        let mut discovered = vec![None; n];
+        let mut low = vec![0; n];
+        let mut counter = 0;
+        let mut bridges = vec![];
+        let mut articulation = vec![false; n];
+
+        for root in 0..n {
+            if discovered[root].is_some() {
+                continue;
+            }
+            discovered[root] = Some(counter);
+            low[root] = counter;
+            counter += 1;
+            let mut root_children = 0;
+            // (node, parent and edge id, next neighbor) triples replace the recursion
+            let mut call_stack = vec![(root, None, 0)];
+
+            while let Some((u, parent, i)) = call_stack.pop() {
+                if let Some(&(v, id)) = undirected[u].get(i) {
+                    call_stack.push((u, parent, i + 1));
+                    // only the edge we came by leads back, not a parallel one
+                    if matches!(parent, Some((_, via)) if via == id) {
+                        continue;
+                    }
+                    match discovered[v] {
+                        None => {
+                            discovered[v] = Some(counter);
+                            low[v] = counter;
+                            counter += 1;
+                            call_stack.push((v, Some((u, id)), 0));
+                        }
+                        Some(d) => low[u] = min(low[u], d),
+                    }
+                } else if let Some((p, _)) = parent {
+                    low[p] = min(low[p], low[u]);
+                    let discovered_p = discovered[p].expect("Parent was discovered");
+                    if low[u] > discovered_p {
+                        bridges.push((self.nodes[p], self.nodes[u]));
+                    }
+                    if p == root {
+                        root_children += 1;
+                    } else if low[u] >= discovered_p {
+                        articulation[p] = true;
+                    }
+                }
+            }
+            articulation[root] = root_children > 1;
+        }
+        let articulation_points = articulation
+            .into_iter()
+            .enumerate()
+            .filter(|(_, a)| *a)
+            .map(|(n, _)| self.nodes[n])
+            .collect();
+        (bridges, articulation_points)
+    }
+
+    /// Links whose failure disconnects the network, treating edges as undirected.
+    pub fn bridges(&self) -> Vec<(KeyType, KeyType)> {
+        self.low_links().0
+    }
+
+    /// Devices whose failure disconnects the network, treating edges as undirected.
+    pub fn articulation_points(&self) -> Vec<KeyType> {
+        self.low_links().1
+    }
+}
+
+impl GraphAccess for InternetOfThings {
+    fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn node_key(&self, index: usize) -> KeyType {
+        self.nodes[index]
+    }
+
+    fn node_index(&self, node: KeyType) -> Option<usize> {
+        self.get_node_index(node)
+    }
+
+    fn out_edges(&self, index: usize) -> &[Edge] {
+        &self.adjacency_list[index]
+    }
+
+    fn has_negative_weights(&self) -> bool {
+        InternetOfThings::has_negative_weights(self)
+    }
+}
+
+pub struct BreadthFirst<'a> {
+    graph: &'a InternetOfThings,
+    queue: VecDeque<usize>,
+    visited: Vec<bool>,
+}
+
+impl<'a> Iterator for BreadthFirst<'a> {
+    type Item = KeyType;
+
+    fn next(&mut self) -> Option<KeyType> {
+        let u = self.queue.pop_front()?;
+        for e in &self.graph.adjacency_list[u] {
+            if !self.visited[e.node] {
+                self.visited[e.node] = true;
+                self.queue.push_back(e.node);
+            }
+        }
+        Some(self.graph.nodes[u])
+    }
+}
+
+pub struct DepthFirst<'a> {
+    graph: &'a InternetOfThings,
+    stack: Vec<usize>,
+    visited: Vec<bool>,
+}
+
+impl<'a> Iterator for DepthFirst<'a> {
+    type Item = KeyType;
+
+    fn next(&mut self) -> Option<KeyType> {
+        while let Some(u) = self.stack.pop() {
+            if self.visited[u] {
+                continue;
+            }
+            self.visited[u] = true;
+            let visited = &self.visited;
+            // reversed, so the first edge is explored first
+            self.stack.extend(
+                self.graph.adjacency_list[u]
+                    .iter()
+                    .rev()
+                    .filter(|e| !visited[e.node])
+                    .map(|e| e.node),
+            );
+            return Some(self.graph.nodes[u]);
         }
+        None
     }
 }
@@ -0,0 +1,493 @@
+use crate::graph::{GraphAccess, GraphError, InternetOfThings};
+use std::error::Error;
+use std::fmt;
+
+type KeyType = u64;
+type WeightType = i32;
+
+/// A problem with the input, and the (1-based) line it occurred on.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseError {
+    Syntax(usize, String),
+    Graph(usize, GraphError),
+}
+
+impl ParseError {
+    pub fn line(&self) -> usize {
+        match self {
+            ParseError::Syntax(line, _) | ParseError::Graph(line, _) => *line,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Syntax(line, message) => write!(f, "line {}: {}", line, message),
+            ParseError::Graph(line, e) => write!(f, "line {}: {}", line, e),
+        }
+    }
+}
+
+impl Error for ParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ParseError::Syntax(..) => None,
+            ParseError::Graph(_, e) => Some(e),
+        }
+    }
+}
+
+fn syntax<T>(line: usize, message: impl Into<String>) -> Result<T, ParseError> {
+    Err(ParseError::Syntax(line, message.into()))
+}
+
+fn parse_node(token: &str, line: usize) -> Result<KeyType, ParseError> {
+    let token = token.trim().trim_matches('"');
+    match token.parse() {
+        Ok(node) => Ok(node),
+        Err(_) => syntax(line, format!("invalid node id `{}`", token)),
+    }
+}
+
+fn parse_weight(token: &str, line: usize) -> Result<WeightType, ParseError> {
+    let token = token.trim().trim_matches('"');
+    match token.parse() {
+        Ok(weight) => Ok(weight),
+        Err(_) => syntax(line, format!("invalid weight `{}`", token)),
+    }
+}
+
+fn add_missing_node(graph: &mut InternetOfThings, node: KeyType) {
+    if graph.node_index(node).is_none() {
+        graph.add_node(node).expect("The node is new");
+    }
+}
+
+fn add_edge(
+    graph: &mut InternetOfThings,
+    (from, to, weight): (KeyType, KeyType, WeightType),
+    line: usize,
+) -> Result<(), ParseError> {
+    graph
+        .insert_edge(from, to, weight)
+        .map_err(|e| ParseError::Graph(line, e))
+}
+
+/// All edges in adjacency order, undirected edges only once.
+fn edge_list(graph: &InternetOfThings) -> Vec<(KeyType, KeyType, WeightType)> {
+    let mut edges = vec![];
+    for u in 0..graph.node_count() {
+        for e in graph.out_edges(u) {
+            if graph.is_directed() || e.target() >= u {
+                edges.push((graph.node_key(u), graph.node_key(e.target()), e.weight()));
+            }
+        }
+    }
+    edges
+}
+
+/// Writes a Graphviz `digraph` (or `graph` if undirected), declaring every
+/// node before the weighted edges.
+pub fn to_dot(graph: &InternetOfThings) -> String {
+    let (kind, op) = if graph.is_directed() {
+        ("digraph", "->")
+    } else {
+        ("graph", "--")
+    };
+    let mut out = format!("{} {{\n", kind);
+    for i in 0..graph.node_count() {
+        out.push_str(&format!("    {};\n", graph.node_key(i)));
+    }
+    for (from, to, weight) in edge_list(graph) {
+        out.push_str(&format!(
+            "    {} {} {} [weight={}];\n",
+            from, op, to, weight
+        ));
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// Reads the subset of DOT that `to_dot` writes: one statement per line,
+/// numeric node ids, and an optional `weight` attribute (default 1) on
+/// edges. Other attributes and `//` comments are ignored.
+pub fn from_dot(input: &str) -> Result<InternetOfThings, ParseError> {
+    let mut graph: Option<InternetOfThings> = None;
+    let mut closed = false;
+    let mut last_line = 0;
+
+    for (i, raw) in input.lines().enumerate() {
+        let line = i + 1;
+        last_line = line;
+        let statement = match raw.find("//") {
+            Some(pos) => &raw[..pos],
+            None => raw,
+        };
+        let statement = statement.trim().trim_end_matches(';').trim();
+        if statement.is_empty() || statement.starts_with('#') {
+            continue;
+        }
+        if closed {
+            return syntax(line, "unexpected content after the closing `}`");
+        }
+
+        let g = match graph {
+            Some(ref mut g) => g,
+            None => {
+                let mut tokens = statement.split_whitespace();
+                let directed = match tokens.next() {
+                    Some("digraph") => true,
+                    Some("graph") => false,
+                    _ => return syntax(line, "expected `digraph` or `graph`"),
+                };
+                if !statement.ends_with('{') {
+                    return syntax(line, "expected `{` after the graph type");
+                }
+                graph = Some(if directed {
+                    InternetOfThings::new()
+                } else {
+                    InternetOfThings::new_undirected()
+                });
+                continue;
+            }
+        };
+
+        if statement == "}" {
+            closed = true;
+            continue;
+        }
+
+        let (body, attributes) = match statement.find('[') {
+            Some(pos) if statement.ends_with(']') => {
+                (&statement[..pos], &statement[pos + 1..statement.len() - 1])
+            }
+            Some(_) => return syntax(line, "unterminated attribute list"),
+            None => (statement, ""),
+        };
+        let body = body.trim();
+        if body == "node" || body == "edge" || body == "graph" || body.contains('=') {
+            // default attributes and graph attributes don't affect the topology
+            continue;
+        }
+
+        let mut weight = 1;
+        for attribute in attributes.split(&[',', ';'][..]) {
+            let mut kv = attribute.splitn(2, '=');
+            if let (Some(key), Some(value)) = (kv.next(), kv.next()) {
+                if key.trim() == "weight" {
+                    weight = parse_weight(value, line)?;
+                }
+            }
+        }
+
+        let (op, wrong_op) = if g.is_directed() {
+            ("->", "--")
+        } else {
+            ("--", "->")
+        };
+        if body.contains(wrong_op) {
+            return syntax(
+                line,
+                format!("`{}` is not allowed here, use `{}`", wrong_op, op),
+            );
+        }
+        let nodes = body
+            .split(op)
+            .map(|n| parse_node(n, line))
+            .collect::<Result<Vec<_>, _>>()?;
+        for n in &nodes {
+            add_missing_node(g, *n);
+        }
+        for pair in nodes.windows(2) {
+            add_edge(g, (pair[0], pair[1], weight), line)?;
+        }
+    }
+
+    match graph {
+        Some(g) if closed => Ok(g),
+        Some(_) => syntax(last_line, "missing closing `}`"),
+        None => syntax(last_line, "missing graph declaration"),
+    }
+}
+
+/// Writes a `from,to,weight` table. Nodes without any edges get a row with
+/// empty `to` and `weight` columns.
+pub fn to_edge_list(graph: &InternetOfThings) -> String {
+    let mut has_edges = vec![false; graph.node_count()];
+    for u in 0..graph.node_count() {
+        for e in graph.out_edges(u) {
+            has_edges[u] = true;
+            has_edges[e.target()] = true;
+        }
+    }
+
+    let mut out = String::from("from,to,weight\n");
+    for (from, to, weight) in edge_list(graph) {
+        out.push_str(&format!("{},{},{}\n", from, to, weight));
+    }
+    for (i, _) in has_edges.iter().enumerate().filter(|(_, e)| !**e) {
+        out.push_str(&format!("{},,\n", graph.node_key(i)));
+    }
+    out
+}
+
+/// Reads a table written by `to_edge_list`; the header row is optional.
+/// Edges are mirrored unless `directed` is set.
+pub fn from_edge_list(input: &str, directed: bool) -> Result<InternetOfThings, ParseError> {
+    let mut graph = if directed {
+        InternetOfThings::new()
+    } else {
+        InternetOfThings::new_undirected()
+    };
+
+    for (i, row) in input.lines().enumerate() {
+        let line = i + 1;
+        let row = row.trim();
+        if row.is_empty() || (i == 0 && row.replace(' ', "") == "from,to,weight") {
+            continue;
+        }
+        let fields: Vec<&str> = row.split(',').map(|f| f.trim()).collect();
+        if fields.len() != 3 {
+            return syntax(line, format!("expected 3 columns, found {}", fields.len()));
+        }
+        let from = parse_node(fields[0], line)?;
+        match (fields[1], fields[2]) {
+            ("", "") => add_missing_node(&mut graph, from),
+            ("", _) | (_, "") => return syntax(line, "`to` and `weight` must both be set"),
+            (to, weight) => {
+                let to = parse_node(to, line)?;
+                let weight = parse_weight(weight, line)?;
+                add_missing_node(&mut graph, from);
+                add_missing_node(&mut graph, to);
+                add_edge(&mut graph, (from, to, weight), line)?;
+            }
+        }
+    }
+    Ok(graph)
+}
+
+/// Writes a GraphML document with an integer `weight` key on edges.
+pub fn to_graphml(graph: &InternetOfThings) -> String {
+    let edge_default = if graph.is_directed() {
+        "directed"
+    } else {
+        "undirected"
+    };
+    let mut out = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n  \
+         <key id=\"weight\" for=\"edge\" attr.name=\"weight\" attr.type=\"int\"/>\n",
+    );
+    out.push_str(&format!(
+        "  <graph id=\"G\" edgedefault=\"{}\">\n",
+        edge_default
+    ));
+    for i in 0..graph.node_count() {
+        out.push_str(&format!("    <node id=\"{}\"/>\n", graph.node_key(i)));
+    }
+    for (from, to, weight) in edge_list(graph) {
+        out.push_str(&format!(
+            "    <edge source=\"{}\" target=\"{}\"><data key=\"weight\">{}</data></edge>\n",
+            from, to, weight
+        ));
+    }
+    out.push_str("  </graph>\n</graphml>\n");
+    out
+}
+
+struct Tag<'a> {
+    name: &'a str,
+    attributes: Vec<(&'a str, &'a str)>,
+    closing: bool,
+    empty: bool,
+    line: usize,
+}
+
+impl<'a> Tag<'a> {
+    fn attribute(&self, name: &str) -> Option<&'a str> {
+        self.attributes.iter().find(|a| a.0 == name).map(|a| a.1)
+    }
+
+    fn require(&self, name: &str) -> Result<&'a str, ParseError> {
+        match self.attribute(name) {
+            Some(value) => Ok(value),
+            None => syntax(
+                self.line,
+                format!("<{}> is missing the `{}` attribute", self.name, name),
+            ),
+        }
+    }
+}
+
+enum Token<'a> {
+    Tag(Tag<'a>),
+    Text(&'a str, usize),
+}
+
+fn parse_tag(body: &str, line: usize) -> Result<Tag<'_>, ParseError> {
+    let closing = body.starts_with('/');
+    let empty = body.ends_with('/');
+    let body = body.trim_start_matches('/').trim_end_matches('/').trim();
+    let name_end = body.find(char::is_whitespace).unwrap_or(body.len());
+    let name = &body[..name_end];
+    if name.is_empty() {
+        return syntax(line, "empty tag");
+    }
+
+    let mut attributes = vec![];
+    let mut rest = body[name_end..].trim_start();
+    while !rest.is_empty() {
+        let eq = match rest.find('=') {
+            Some(eq) => eq,
+            None => return syntax(line, format!("malformed attribute in <{}>", name)),
+        };
+        let key = rest[..eq].trim();
+        let value = rest[eq + 1..].trim_start();
+        let quote = match value.chars().next() {
+            Some(q) if q == '"' || q == '\'' => q,
+            _ => return syntax(line, format!("unquoted value for `{}`", key)),
+        };
+        let end = match value[1..].find(quote) {
+            Some(end) => end + 1,
+            None => return syntax(line, format!("unterminated value for `{}`", key)),
+        };
+        attributes.push((key, &value[1..end]));
+        rest = value[end + 1..].trim_start();
+    }
+    Ok(Tag {
+        name,
+        attributes,
+        closing,
+        empty,
+        line,
+    })
+}
+
+fn xml_tokens(input: &str) -> Result<Vec<Token<'_>>, ParseError> {
+    let mut tokens = vec![];
+    let mut line = 1;
+    let mut rest = input;
+    while let Some(start) = rest.find('<') {
+        let text = &rest[..start];
+        if !text.trim().is_empty() {
+            tokens.push(Token::Text(text.trim(), line));
+        }
+        line += text.matches('\n').count();
+        rest = &rest[start..];
+
+        let terminator = if rest.starts_with("<!--") {
+            "-->"
+        } else if rest.starts_with("<?") {
+            "?>"
+        } else {
+            ">"
+        };
+        let end = match rest.find(terminator) {
+            Some(end) => end,
+            None => return syntax(line, "unterminated markup"),
+        };
+        if !rest.starts_with("<!") && !rest.starts_with("<?") {
+            tokens.push(Token::Tag(parse_tag(&rest[1..end], line)?));
+        }
+        line += rest[..end].matches('\n').count();
+        rest = &rest[end + terminator.len()..];
+    }
+    if !rest.trim().is_empty() {
+        return syntax(line, "text after the last element");
+    }
+    Ok(tokens)
+}
+
+/// Reads a single `<graph>` from a GraphML document. Edge weights come from
+/// the `<data>` key named `weight` and default to 1.
+pub fn from_graphml(input: &str) -> Result<InternetOfThings, ParseError> {
+    let mut weight_keys = vec!["weight"];
+    let mut graph: Option<InternetOfThings> = None;
+    let mut nodes = vec![];
+    let mut edges = vec![];
+    // an open <edge>, and whether we are inside its weight <data>
+    let mut edge: Option<(usize, KeyType, KeyType, WeightType)> = None;
+    let mut in_weight = false;
+
+    for token in xml_tokens(input)? {
+        let tag = match token {
+            Token::Text(text, line) => {
+                match edge {
+                    Some(ref mut e) if in_weight => e.3 = parse_weight(text, line)?,
+                    _ => (),
+                }
+                continue;
+            }
+            Token::Tag(tag) => tag,
+        };
+        let line = tag.line;
+
+        if tag.closing {
+            match tag.name {
+                "edge" => edges.extend(edge.take()),
+                "data" => in_weight = false,
+                _ => (),
+            }
+            continue;
+        }
+        match tag.name {
+            "key" => {
+                if tag.attribute("attr.name") == Some("weight") {
+                    weight_keys.push(tag.require("id")?);
+                }
+            }
+            "graph" => {
+                if graph.is_some() {
+                    return syntax(line, "only a single <graph> is supported");
+                }
+                graph = Some(match tag.attribute("edgedefault") {
+                    Some("undirected") => InternetOfThings::new_undirected(),
+                    Some("directed") | None => InternetOfThings::new(),
+                    Some(other) => return syntax(line, format!("unknown edgedefault `{}`", other)),
+                });
+            }
+            "node" | "edge" if graph.is_none() => {
+                return syntax(line, format!("<{}> outside of <graph>", tag.name));
+            }
+            "node" => nodes.push((line, parse_node(tag.require("id")?, line)?)),
+            "edge" => {
+                if edge.is_some() {
+                    return syntax(line, "nested <edge>");
+                }
+                let source = parse_node(tag.require("source")?, line)?;
+                let target = parse_node(tag.require("target")?, line)?;
+                let e = (line, source, target, 1);
+                if tag.empty {
+                    edges.push(e);
+                } else {
+                    edge = Some(e);
+                }
+            }
+            "data" => {
+                in_weight = !tag.empty
+                    && edge.is_some()
+                    && weight_keys.iter().any(|k| Some(*k) == tag.attribute("key"));
+            }
+            _ => (),
+        }
+    }
+    if let Some((line, ..)) = edge {
+        return syntax(line, "unclosed <edge>");
+    }
+
+    // edges may refer to nodes that are declared further down
+    let mut graph = match graph {
+        Some(g) => g,
+        None => return syntax(input.lines().count(), "missing <graph>"),
+    };
+    for (line, node) in nodes {
+        graph
+            .add_node(node)
+            .map_err(|e| ParseError::Graph(line, e))?;
+    }
+    for (line, from, to, weight) in edges {
+        add_edge(&mut graph, (from, to, weight), line)?;
+    }
+    Ok(graph)
+}
@@ -1,10 +1,41 @@
 use crate::MessageNotification;
 use std::boxed::Box;
-use std::mem;
+
+pub type Handle = usize;
+
+/// A max-priority queue of notifications, ordered by `no_messages`.
+///
+/// `push` returns a handle that stays valid until the notification is
+/// popped, or until the queue is merged into another one. Handles of popped
+/// notifications are handed out again by later pushes.
+pub trait PriorityQueue {
+    fn push(&mut self, notification: MessageNotification) -> Handle;
+    fn pop(&mut self) -> Option<MessageNotification>;
+    fn peek(&self) -> Option<&MessageNotification>;
+
+    /// Moves a notification towards the front of the queue by raising its
+    /// message count (the decrease-key operation of a max-heap). Returns
+    /// `false` if the handle is stale or `no_messages` is lower than the
+    /// current count.
+    fn decrease_key(&mut self, handle: Handle, no_messages: u64) -> bool;
+
+    /// Moves all notifications of `other` into this queue.
+    fn merge(&mut self, other: Self);
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
 
 pub struct MessageChecker {
     pub length: usize,
     heap: Vec<Box<MessageNotification>>,
+    // the handle of each heap entry, and the 1-based position of each handle
+    handles: Vec<Handle>,
+    positions: Vec<Option<usize>>,
+    free: Vec<Handle>,
 }
 
 impl MessageChecker {
@@ -12,12 +43,17 @@ impl MessageChecker {
         MessageChecker {
             length: 0,
             heap: vec![],
+            handles: vec![],
+            positions: vec![],
+            free: vec![],
         }
     }
 
     fn swap(&mut self, pos1: usize, pos2: usize) {
-        let m2 = self.heap[pos1 - 1].clone();
-        self.heap[pos1 - 1] = mem::replace(&mut self.heap[pos2 - 1], m2);
+        self.heap.swap(pos1 - 1, pos2 - 1);
+        self.handles.swap(pos1 - 1, pos2 - 1);
+        self.positions[self.handles[pos1 - 1]] = Some(pos1);
+        self.positions[self.handles[pos2 - 1]] = Some(pos2);
     }
 
     fn has_more_messages(&self, pos1: usize, pos2: usize) -> bool {
@@ -26,45 +62,108 @@ impl MessageChecker {
         a.no_messages >= b.no_messages
     }
 
-    pub fn add(&mut self, notification: MessageNotification) {
-        self.heap.push(Box::new(notification));
-        self.length = self.heap.len();
+    fn sift_up(&mut self, mut i: usize) {
+        while i / 2 > 0 && self.has_more_messages(i, i / 2) {
+            self.swap(i, i / 2);
+            i /= 2;
+        }
+    }
 
-        if self.length > 1 {
-            let mut i = self.length;
-            while i / 2 > 0 && self.has_more_messages(i, i / 2) {
-                self.swap(i, i / 2);
-                i /= 2;
+    fn sift_down(&mut self, mut i: usize) {
+        while i * 2 <= self.length {
+            let children = (i * 2, i * 2 + 1);
+            let child = if children.1 <= self.length
+                && !self.has_more_messages(children.0, children.1)
+            {
+                children.1
+            } else {
+                children.0
+            };
+            if self.has_more_messages(i, child) {
+                break;
             }
+            self.swap(i, child);
+            i = child;
         }
     }
 
+    pub fn add(&mut self, notification: MessageNotification) {
+        self.push(notification);
+    }
+
     pub fn pop(&mut self) -> Option<MessageNotification> {
         if self.length > 0 {
             let elem = self.heap.swap_remove(0);
+            let handle = self.handles.swap_remove(0);
+            self.positions[handle] = None;
+            self.free.push(handle);
             self.length = self.heap.len();
-            let mut i = 1;
-            while i * 2 < self.length {
-                let children = (i * 2, i * 2 + 1);
-                i = if self.has_more_messages(children.0, children.1) {
-                    if self.has_more_messages(children.0, i) {
-                        self.swap(i, children.0);
-                        children.0
-                    } else {
-                        break;
-                    }
-                } else {
-                    if self.has_more_messages(children.1, i) {
-                        self.swap(i, children.1);
-                        children.1
-                    } else {
-                        break;
-                    }
-                }
+            if self.length > 0 {
+                self.positions[self.handles[0]] = Some(1);
+                self.sift_down(1);
             }
             Some(*elem)
         } else {
             None
         }
     }
+
+    pub fn is_valid_heap(&self) -> bool {
+        self.length == self.heap.len()
+            && (2..=self.length).all(|i| self.has_more_messages(i / 2, i))
+            && self
+                .handles
+                .iter()
+                .enumerate()
+                .all(|(i, h)| self.positions[*h] == Some(i + 1))
+    }
+}
+
+impl PriorityQueue for MessageChecker {
+    fn push(&mut self, notification: MessageNotification) -> Handle {
+        self.heap.push(Box::new(notification));
+        self.length = self.heap.len();
+        let handle = match self.free.pop() {
+            Some(handle) => {
+                self.positions[handle] = Some(self.length);
+                handle
+            }
+            None => {
+                self.positions.push(Some(self.length));
+                self.positions.len() - 1
+            }
+        };
+        self.handles.push(handle);
+        self.sift_up(self.length);
+        handle
+    }
+
+    fn pop(&mut self) -> Option<MessageNotification> {
+        MessageChecker::pop(self)
+    }
+
+    fn peek(&self) -> Option<&MessageNotification> {
+        self.heap.first().map(|n| n.as_ref())
+    }
+
+    fn decrease_key(&mut self, handle: Handle, no_messages: u64) -> bool {
+        match self.positions.get(handle) {
+            Some(&Some(i)) if self.heap[i - 1].no_messages <= no_messages => {
+                self.heap[i - 1].no_messages = no_messages;
+                self.sift_up(i);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn merge(&mut self, other: MessageChecker) {
+        for notification in other.heap {
+            self.push(*notification);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.length
+    }
 }
@@ -2,8 +2,14 @@
 
 mod binary_search_tree;
 mod btree;
+mod csr_graph;
+mod dary_heap;
+mod fibonacci_heap;
+mod generic_graph;
 mod graph;
+mod graph_io;
 mod heap;
+mod pairing_heap;
 mod red_black_tree;
 mod trie;
 
@@ -54,11 +60,14 @@ impl PartialEq for MessageNotification {
 #[cfg(test)]
 mod tests {
     extern crate test;
+    use crate::graph::GraphAccess;
+    use crate::heap::PriorityQueue;
     use crate::*;
     use rand::thread_rng;
     use rand::Rng;
     use std::cell::RefCell;
-    use std::collections::HashSet;
+    use std::cmp::min;
+    use std::collections::{HashMap, HashSet};
     use std::iter::FromIterator;
     use test::Bencher;
 
@@ -89,52 +98,52 @@ mod tests {
                 (1, items[3].numerical_id.clone()),
                 (10, items[9].numerical_id.clone()),
             ],
-        );
+        ).unwrap();
 
         g.set_edges(
             items[1].numerical_id.clone(),
             vec![(1, items[0].numerical_id.clone())],
-        );
+        ).unwrap();
         g.set_edges(
             items[2].numerical_id.clone(),
             vec![(1, items[0].numerical_id.clone())],
-        );
+        ).unwrap();
         g.set_edges(
             items[3].numerical_id.clone(),
             vec![
                 (1, items[0].numerical_id.clone()),
                 (1, items[4].numerical_id.clone()),
             ],
-        );
+        ).unwrap();
         g.set_edges(
             items[4].numerical_id.clone(),
             vec![
                 (1, items[3].numerical_id.clone()),
                 (1, items[5].numerical_id.clone()),
             ],
-        );
+        ).unwrap();
         g.set_edges(
             items[5].numerical_id.clone(),
             vec![
                 (1, items[4].numerical_id.clone()),
                 (1, items[6].numerical_id.clone()),
             ],
-        );
+        ).unwrap();
         g.set_edges(
             items[6].numerical_id.clone(),
             vec![
                 (1, items[9].numerical_id.clone()),
                 (1, items[5].numerical_id.clone()),
             ],
-        );
+        ).unwrap();
         g.set_edges(
             items[7].numerical_id.clone(),
             vec![(1, items[9].numerical_id.clone())],
-        );
+        ).unwrap();
         g.set_edges(
             items[8].numerical_id.clone(),
             vec![(1, items[9].numerical_id.clone())],
-        );
+        ).unwrap();
         g.set_edges(
             items[9].numerical_id.clone(),
             vec![
@@ -143,7 +152,7 @@ mod tests {
                 (1, items[6].numerical_id.clone()),
                 (10, items[0].numerical_id.clone()),
             ],
-        );
+        ).unwrap();
         g
     }
 
@@ -437,6 +446,291 @@ mod tests {
         assert_eq!(heap.pop(), Some(a));
     }
 
+    #[test]
+    fn binary_heap_pop_left_child_only() {
+        let mut heap = heap::MessageChecker::new_empty();
+
+        for (i, n) in [10, 9, 1].iter().enumerate() {
+            heap.add(new_notification_with_id(i as u64, *n));
+        }
+        assert!(heap.is_valid_heap());
+
+        // after the first pop, the root only has a left child
+        assert_eq!(heap.pop().map(|n| n.no_messages), Some(10));
+        assert!(heap.is_valid_heap());
+        assert_eq!(heap.pop().map(|n| n.no_messages), Some(9));
+        assert_eq!(heap.pop().map(|n| n.no_messages), Some(1));
+        assert_eq!(heap.length, 0);
+        assert_eq!(heap.pop(), None);
+    }
+
+    #[test]
+    fn binary_heap_pop_matches_std() {
+        let mut rng = thread_rng();
+
+        for _ in 0..100 {
+            let mut heap = heap::MessageChecker::new_empty();
+            let mut reference = std::collections::BinaryHeap::new();
+            let len = rng.gen_range(0, 50);
+
+            for i in 0..len {
+                let no_messages = rng.gen_range(0, 20);
+                heap.add(new_notification_with_id(i, no_messages));
+                reference.push(no_messages);
+                assert!(heap.is_valid_heap());
+            }
+
+            while let Some(expected) = reference.pop() {
+                assert_eq!(heap.pop().map(|n| n.no_messages), Some(expected));
+                assert!(heap.is_valid_heap());
+            }
+            assert_eq!(heap.pop(), None);
+        }
+    }
+
+    fn priority_queue_pop_in_order<Q: PriorityQueue>(mut queue: Q) {
+        let a = new_notification_with_id(1, 40);
+        let b = new_notification_with_id(2, 300);
+        let c = new_notification_with_id(3, 50);
+        let d = new_notification_with_id(4, 500);
+
+        assert_eq!(queue.peek(), None);
+        queue.push(a.clone());
+        queue.push(b.clone());
+        queue.push(c.clone());
+        queue.push(d.clone());
+
+        assert_eq!(queue.len(), 4);
+        assert_eq!(queue.peek(), Some(&d));
+
+        assert_eq!(queue.pop(), Some(d));
+        assert_eq!(queue.pop(), Some(b));
+        assert_eq!(queue.pop(), Some(c));
+        assert_eq!(queue.pop(), Some(a));
+        assert_eq!(queue.pop(), None);
+        assert!(queue.is_empty());
+    }
+
+    fn priority_queue_decrease_key<Q: PriorityQueue>(mut queue: Q) {
+        let handles: Vec<heap::Handle> = (0..10)
+            .map(|i| queue.push(new_notification_with_id(i, i * 10)))
+            .collect();
+        queue.pop();
+
+        assert!(queue.decrease_key(handles[3], 1000));
+        assert!(!queue.decrease_key(handles[4], 1));
+        assert!(!queue.decrease_key(handles[9], 1000));
+        assert_eq!(queue.peek().map(|n| n.device.numerical_id), Some(3));
+
+        assert!(queue.decrease_key(handles[5], 1000));
+        let popped: Vec<u64> = (0..9)
+            .filter_map(|_| queue.pop())
+            .map(|n| n.device.numerical_id)
+            .collect();
+        assert_eq!(popped[2..], [8, 7, 6, 4, 2, 1, 0]);
+        assert!(popped[..2].contains(&3) && popped[..2].contains(&5));
+    }
+
+    fn priority_queue_reuses_handles<Q: PriorityQueue>(mut queue: Q) {
+        let low = queue.push(new_notification_with_id(1, 10));
+        let high = queue.push(new_notification_with_id(2, 20));
+        queue.pop();
+        assert!(!queue.decrease_key(high, 30));
+
+        let reused = queue.push(new_notification_with_id(3, 5));
+        assert_eq!(reused, high);
+        assert!(queue.decrease_key(reused, 15));
+        assert!(queue.decrease_key(low, 10));
+        assert_eq!(queue.pop().map(|n| n.device.numerical_id), Some(3));
+        assert_eq!(queue.pop().map(|n| n.device.numerical_id), Some(1));
+    }
+
+    fn priority_queue_merge<Q: PriorityQueue>(mut a: Q, mut b: Q) {
+        let handle = a.push(new_notification_with_id(1, 10));
+        a.push(new_notification_with_id(2, 30));
+        b.push(new_notification_with_id(3, 20));
+        b.push(new_notification_with_id(4, 40));
+
+        a.merge(b);
+        assert_eq!(a.len(), 4);
+        assert!(a.decrease_key(handle, 50));
+
+        let popped: Vec<u64> = (0..4)
+            .filter_map(|_| a.pop())
+            .map(|n| n.device.numerical_id)
+            .collect();
+        assert_eq!(popped, vec![1, 4, 2, 3]);
+    }
+
+    fn priority_queue_matches_std<Q: PriorityQueue>(
+        new: impl Fn() -> Q,
+        is_valid: impl Fn(&Q) -> bool,
+    ) {
+        let mut rng = thread_rng();
+
+        for _ in 0..50 {
+            let mut queue = new();
+            let mut other = new();
+            let mut live = HashMap::new();
+            let mut reference = std::collections::BinaryHeap::new();
+            let len = rng.gen_range(0, 50);
+
+            for i in 0..len {
+                let no_messages = rng.gen_range(0, 100);
+                if rng.gen() {
+                    let handle = queue.push(new_notification_with_id(i, no_messages));
+                    live.insert(i, (handle, no_messages));
+                } else {
+                    other.push(new_notification_with_id(i, no_messages));
+                    reference.push(no_messages);
+                }
+                if rng.gen_range(0, 4) == 0 {
+                    if let Some(n) = queue.pop() {
+                        let (_, max) = live.remove(&n.device.numerical_id).unwrap();
+                        assert_eq!(n.no_messages, max);
+                        assert!(live.values().all(|v| v.1 <= max));
+                    }
+                }
+                assert!(is_valid(&queue));
+            }
+
+            for (handle, no_messages) in live.values_mut() {
+                if rng.gen() {
+                    *no_messages += rng.gen_range(0, 100);
+                    assert!(queue.decrease_key(*handle, *no_messages));
+                    assert!(is_valid(&queue));
+                }
+            }
+            reference.extend(live.values().map(|v| v.1));
+
+            queue.merge(other);
+            assert!(is_valid(&queue));
+            assert_eq!(queue.len(), reference.len());
+
+            while let Some(expected) = reference.pop() {
+                assert_eq!(queue.peek().map(|n| n.no_messages), Some(expected));
+                assert_eq!(queue.pop().map(|n| n.no_messages), Some(expected));
+                assert!(is_valid(&queue));
+            }
+            assert_eq!(queue.pop(), None);
+        }
+    }
+
+    #[test]
+    fn binary_heap_priority_queue() {
+        priority_queue_pop_in_order(heap::MessageChecker::new_empty());
+        priority_queue_decrease_key(heap::MessageChecker::new_empty());
+        priority_queue_reuses_handles(heap::MessageChecker::new_empty());
+        priority_queue_merge(
+            heap::MessageChecker::new_empty(),
+            heap::MessageChecker::new_empty(),
+        );
+        priority_queue_matches_std(heap::MessageChecker::new_empty, |q| q.is_valid_heap());
+    }
+
+    #[test]
+    fn dary_heap_priority_queue() {
+        for arity in 2..6 {
+            priority_queue_pop_in_order(dary_heap::DAryHeap::new_empty(arity));
+            priority_queue_decrease_key(dary_heap::DAryHeap::new_empty(arity));
+            priority_queue_reuses_handles(dary_heap::DAryHeap::new_empty(arity));
+            priority_queue_merge(
+                dary_heap::DAryHeap::new_empty(arity),
+                dary_heap::DAryHeap::new_empty(arity),
+            );
+            priority_queue_matches_std(
+                || dary_heap::DAryHeap::new_empty(arity),
+                |q| q.is_valid_heap(),
+            );
+        }
+    }
+
+    #[test]
+    fn pairing_heap_priority_queue() {
+        priority_queue_pop_in_order(pairing_heap::PairingHeap::new_empty());
+        priority_queue_decrease_key(pairing_heap::PairingHeap::new_empty());
+        priority_queue_reuses_handles(pairing_heap::PairingHeap::new_empty());
+        priority_queue_merge(
+            pairing_heap::PairingHeap::new_empty(),
+            pairing_heap::PairingHeap::new_empty(),
+        );
+        priority_queue_matches_std(pairing_heap::PairingHeap::new_empty, |q| {
+            q.is_valid_heap()
+        });
+    }
+
+    #[test]
+    fn fibonacci_heap_priority_queue() {
+        priority_queue_pop_in_order(fibonacci_heap::FibonacciHeap::new_empty());
+        priority_queue_decrease_key(fibonacci_heap::FibonacciHeap::new_empty());
+        priority_queue_reuses_handles(fibonacci_heap::FibonacciHeap::new_empty());
+        priority_queue_merge(
+            fibonacci_heap::FibonacciHeap::new_empty(),
+            fibonacci_heap::FibonacciHeap::new_empty(),
+        );
+        priority_queue_matches_std(fibonacci_heap::FibonacciHeap::new_empty, |q| {
+            q.is_valid_heap()
+        });
+    }
+
+    fn bench_priority_queue<Q: PriorityQueue>(b: &mut Bencher, new: impl Fn() -> Q) {
+        let mut rng = thread_rng();
+        let items: Vec<MessageNotification> = (0..LIST_ITEMS)
+            .map(|i| new_notification_with_id(i, rng.gen_range(0, LIST_ITEMS)))
+            .collect();
+
+        b.iter(|| {
+            let mut queue = new();
+            let handles: Vec<heap::Handle> =
+                items.iter().map(|n| queue.push(n.clone())).collect();
+            for (i, h) in handles.iter().enumerate().step_by(10) {
+                queue.decrease_key(*h, LIST_ITEMS + i as u64);
+            }
+            while queue.pop().is_some() {}
+        });
+    }
+
+    #[bench]
+    fn bench_binary_heap_push_pop(b: &mut Bencher) {
+        let mut rng = thread_rng();
+        let items: Vec<MessageNotification> = (0..LIST_ITEMS)
+            .map(|i| new_notification_with_id(i, rng.gen_range(0, LIST_ITEMS)))
+            .collect();
+
+        b.iter(|| {
+            let mut heap = heap::MessageChecker::new_empty();
+            for n in items.iter() {
+                heap.add(n.clone());
+            }
+            while heap.pop().is_some() {}
+        });
+    }
+
+    #[bench]
+    fn bench_dary_heap_2_push_pop(b: &mut Bencher) {
+        bench_priority_queue(b, || dary_heap::DAryHeap::new_empty(2));
+    }
+
+    #[bench]
+    fn bench_dary_heap_4_push_pop(b: &mut Bencher) {
+        bench_priority_queue(b, || dary_heap::DAryHeap::new_empty(4));
+    }
+
+    #[bench]
+    fn bench_dary_heap_8_push_pop(b: &mut Bencher) {
+        bench_priority_queue(b, || dary_heap::DAryHeap::new_empty(8));
+    }
+
+    #[bench]
+    fn bench_pairing_heap_push_pop(b: &mut Bencher) {
+        bench_priority_queue(b, pairing_heap::PairingHeap::new_empty);
+    }
+
+    #[bench]
+    fn bench_fibonacci_heap_push_pop(b: &mut Bencher) {
+        bench_priority_queue(b, fibonacci_heap::FibonacciHeap::new_empty);
+    }
+
     #[bench]
     fn bench_unsorted_insert_btree_find_7(b: &mut Bencher) {
         let mut tree = btree::DeviceDatabase::new_empty(7);
@@ -694,6 +988,1177 @@ mod tests {
         )
     }
 
+    #[test]
+    fn graph_find_shortest_path_unreachable() {
+        let len = 10;
+        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+
+        let mut g = build_graph(graph::InternetOfThings::new(), &items);
+        g.set_edges(100, vec![(1, items[0].numerical_id)]).unwrap();
+
+        assert_eq!(g.shortest_path(items[0].numerical_id, 100), None);
+        assert_eq!(g.shortest_path(items[0].numerical_id, 101), None);
+        assert_eq!(
+            g.shortest_path(100, items[2].numerical_id),
+            Some((2, vec![100, items[0].numerical_id, items[2].numerical_id]))
+        );
+    }
+
+    #[test]
+    fn graph_shortest_paths_from() {
+        let len = 10;
+        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+
+        let g = build_graph(graph::InternetOfThings::new(), &items);
+        let paths = g.shortest_paths_from(items[0].numerical_id).unwrap();
+
+        assert_eq!(paths.len(), len as usize);
+        assert_eq!(paths[&items[0].numerical_id], (0, vec![items[0].numerical_id]));
+        assert_eq!(
+            paths[&items[8].numerical_id],
+            (
+                6,
+                vec![
+                    items[0].numerical_id,
+                    items[3].numerical_id,
+                    items[4].numerical_id,
+                    items[5].numerical_id,
+                    items[6].numerical_id,
+                    items[9].numerical_id,
+                    items[8].numerical_id
+                ]
+            )
+        );
+        for item in items.iter() {
+            assert_eq!(
+                g.shortest_path(items[0].numerical_id, item.numerical_id).map(|p| p.0),
+                Some(paths[&item.numerical_id].0)
+            );
+        }
+        assert_eq!(g.shortest_paths_from(100), None);
+    }
+
+    fn floyd_warshall(ids: &[u64], edges: &HashMap<(u64, u64), i32>) -> Vec<Vec<Option<i64>>> {
+        let n = ids.len();
+        let mut dist = vec![vec![None; n]; n];
+        for i in 0..n {
+            dist[i][i] = Some(0);
+            for j in 0..n {
+                if let Some(w) = edges.get(&(ids[i], ids[j])) {
+                    dist[i][j] = Some(min(*w as i64, dist[i][j].unwrap_or(i64::MAX)));
+                }
+            }
+        }
+        for k in 0..n {
+            for i in 0..n {
+                for j in 0..n {
+                    if let (Some(a), Some(b)) = (dist[i][k], dist[k][j]) {
+                        let shorter = match dist[i][j] {
+                            Some(d) => a + b < d,
+                            None => true,
+                        };
+                        if shorter {
+                            dist[i][j] = Some(a + b);
+                        }
+                    }
+                }
+            }
+        }
+        dist
+    }
+
+    #[test]
+    fn graph_shortest_path_matches_floyd_warshall() {
+        let mut rng = thread_rng();
+
+        for _ in 0..20 {
+            let len = rng.gen_range(1, 30);
+            // ids that differ from their positions
+            let mut ids: Vec<u64> = (0..len).map(|i| i * 7 + 3).collect();
+            rng.shuffle(&mut ids);
+
+            let mut g = graph::InternetOfThings::new();
+            g.set_nodes(ids.clone());
+            let mut edges = HashMap::new();
+            for from in ids.iter() {
+                // some nodes stay unreachable or isolated
+                let out: Vec<(i32, u64)> = (0..rng.gen_range(0, 4))
+                    .map(|_| (rng.gen_range(0, 20), *rng.choose(&ids).unwrap()))
+                    .collect();
+                for (w, to) in out.iter() {
+                    let e = edges.entry((*from, *to)).or_insert(*w);
+                    *e = min(*e, *w);
+                }
+                g.set_edges(*from, out).unwrap();
+            }
+
+            let dist = floyd_warshall(&ids, &edges);
+            for (i, from) in ids.iter().enumerate() {
+                let all = g.shortest_paths_from(*from).unwrap();
+                for (j, to) in ids.iter().enumerate() {
+                    let actual = g.shortest_path(*from, *to);
+                    assert_eq!(actual.as_ref().map(|p| p.0), dist[i][j]);
+                    assert_eq!(all.get(to).map(|p| p.0), dist[i][j]);
+
+                    if let Some((cost, path)) = actual {
+                        assert_eq!(path.first(), Some(from));
+                        assert_eq!(path.last(), Some(to));
+                        let total: i32 = path.windows(2).map(|w| edges[&(w[0], w[1])]).sum();
+                        assert_eq!(total as i64, cost);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn graph_bellman_ford_negative_weights() {
+        let mut g = graph::InternetOfThings::new();
+        g.set_nodes(vec![1, 2, 3, 4]);
+        g.set_edges(1, vec![(4, 2), (5, 3)]).unwrap();
+        g.set_edges(3, vec![(-3, 2)]).unwrap();
+        g.set_edges(2, vec![(2, 4)]).unwrap();
+
+        assert!(g.has_negative_weights());
+        assert_eq!(g.shortest_path(1, 4), Some((4, vec![1, 3, 2, 4])));
+        assert_eq!(g.a_star(1, 4, |_, _| 0), Some((4, vec![1, 3, 2, 4])));
+        assert_eq!(g.shortest_paths_from(1).unwrap()[&2], (2, vec![1, 3, 2]));
+        // reachable, for less than nothing
+        assert_eq!(g.shortest_path(3, 4), Some((-1, vec![3, 2, 4])));
+        assert_eq!(g.a_star(3, 2, |_, _| 0), Some((-3, vec![3, 2])));
+        assert_eq!(g.shortest_paths_from(3).unwrap()[&4], (-1, vec![3, 2, 4]));
+        assert_eq!(g.shortest_paths_from(3).unwrap().len(), 3);
+        assert_eq!(g.shortest_path(4, 3), None);
+
+        let paths = g.bellman_ford(1).unwrap().unwrap();
+        assert_eq!(paths[&2], (2, vec![1, 3, 2]));
+        assert_eq!(paths[&4], (4, vec![1, 3, 2, 4]));
+        assert_eq!(g.bellman_ford(5), None);
+
+        let all = g.johnson().unwrap();
+        assert_eq!(all[&1], paths);
+        assert_eq!(all[&3][&4], (-1, vec![3, 2, 4]));
+        assert_eq!(all[&4].len(), 1);
+
+        g.set_edges(3, vec![(3, 2)]).unwrap();
+        assert!(!g.has_negative_weights());
+        assert_eq!(g.shortest_path(1, 4), Some((6, vec![1, 2, 4])));
+    }
+
+    #[test]
+    fn graph_shortest_path_costs_beyond_u32() {
+        let mut g = graph::InternetOfThings::new();
+        g.set_nodes(vec![1, 2, 3]);
+        g.add_edge(1, 2, i32::MAX).unwrap();
+        g.add_edge(2, 3, i32::MAX).unwrap();
+
+        let cost = 2 * i32::MAX as i64;
+        assert_eq!(g.shortest_path(1, 3), Some((cost, vec![1, 2, 3])));
+        assert_eq!(g.a_star(1, 3, |_, _| 0), Some((cost, vec![1, 2, 3])));
+        assert_eq!(g.k_shortest_paths(1, 3, 2), vec![(cost as u64, vec![1, 2, 3])]);
+    }
+
+    #[test]
+    fn graph_bellman_ford_negative_cycle() {
+        let mut g = graph::InternetOfThings::new();
+        g.set_nodes(vec![1, 2, 3, 4, 5]);
+        g.set_edges(1, vec![(1, 2)]).unwrap();
+        g.set_edges(2, vec![(1, 3)]).unwrap();
+        g.set_edges(3, vec![(1, 4)]).unwrap();
+        g.set_edges(4, vec![(-5, 2)]).unwrap();
+
+        let cycle = g.bellman_ford(1).unwrap().unwrap_err();
+        let mut nodes = cycle.0.clone();
+        nodes.sort();
+        assert_eq!(nodes, vec![2, 3, 4]);
+        let start = cycle.0.iter().position(|n| *n == 2).unwrap();
+        let rotated: Vec<u64> = cycle.0.iter().cycle().skip(start).take(3).cloned().collect();
+        assert_eq!(rotated, vec![2, 3, 4]);
+
+        // the cycle is not reachable from 5
+        assert_eq!(g.bellman_ford(5).unwrap().map(|p| p.len()), Ok(1));
+        assert_eq!(g.shortest_path(1, 4), None);
+        assert_eq!(g.shortest_paths_from(1), None);
+        g.add_node(6).unwrap();
+        g.set_edges(5, vec![(2, 6)]).unwrap();
+        assert_eq!(g.shortest_path(5, 6), Some((2, vec![5, 6])));
+        assert_eq!(g.a_star(5, 6, |_, _| 0), Some((2, vec![5, 6])));
+        assert!(g.johnson().is_err());
+    }
+
+    #[test]
+    fn graph_johnson_matches_floyd_warshall() {
+        let mut rng = thread_rng();
+
+        for _ in 0..20 {
+            let len = rng.gen_range(1, 30);
+            let ids: Vec<u64> = (0..len).collect();
+            // potentials keep every cycle non-negative
+            let potential: Vec<i32> = ids.iter().map(|_| rng.gen_range(-20, 20)).collect();
+
+            let mut g = graph::InternetOfThings::new();
+            g.set_nodes(ids.clone());
+            let mut edges = HashMap::new();
+            for from in ids.iter() {
+                let out: Vec<(i32, u64)> = (0..rng.gen_range(0, 4))
+                    .map(|_| {
+                        let to = *rng.choose(&ids).unwrap();
+                        let w = rng.gen_range(0, 20) + potential[*from as usize]
+                            - potential[to as usize];
+                        (w, to)
+                    })
+                    .collect();
+                for (w, to) in out.iter() {
+                    let e = edges.entry((*from, *to)).or_insert(*w);
+                    *e = min(*e, *w);
+                }
+                g.set_edges(*from, out).unwrap();
+            }
+
+            let dist = floyd_warshall(&ids, &edges);
+            let all = g.johnson().unwrap();
+            for (i, from) in ids.iter().enumerate() {
+                let paths = g.bellman_ford(*from).unwrap().unwrap();
+                for (j, to) in ids.iter().enumerate() {
+                    assert_eq!(paths.get(to).map(|p| p.0), dist[i][j]);
+                    assert_eq!(all[from].get(to).map(|p| p.0), dist[i][j]);
+
+                    if let Some((cost, path)) = all[from].get(to) {
+                        let total: i64 =
+                            path.windows(2).map(|w| edges[&(w[0], w[1])] as i64).sum();
+                        assert_eq!(total, *cost);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn graph_a_star_find_shortest_path() {
+        let len = 10;
+        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+
+        let g = build_graph(graph::InternetOfThings::new(), &items);
+        let from = items[0].numerical_id;
+        let to = items[9].numerical_id;
+
+        assert_eq!(g.a_star(from, to, |_, _| 0), g.shortest_path(from, to));
+        assert_eq!(g.a_star(from, from, |_, _| 0), Some((0, vec![from])));
+        assert_eq!(g.a_star(from, 100, |_, _| 0), None);
+    }
+
+    #[test]
+    fn graph_a_star_matches_dijkstra() {
+        let mut rng = thread_rng();
+        let len = 200;
+
+        for _ in 0..10 {
+            let coordinates: Vec<(i64, i64)> = (0..len)
+                .map(|_| (rng.gen_range(0, 100), rng.gen_range(0, 100)))
+                .collect();
+            let manhattan = |a: u64, b: u64| {
+                let (a, b) = (coordinates[a as usize], coordinates[b as usize]);
+                ((a.0 - b.0).abs() + (a.1 - b.1).abs()) as u32
+            };
+
+            let mut g = graph::InternetOfThings::new();
+            g.set_nodes((0..len).collect());
+            for from in 0..len {
+                // links are never shorter than the distance they cover
+                let edges = (0..3)
+                    .map(|_| {
+                        let to = rng.gen_range(0, len);
+                        ((manhattan(from, to) + rng.gen_range(0, 10)) as i32, to)
+                    })
+                    .collect();
+                g.set_edges(from, edges).unwrap();
+            }
+
+            for _ in 0..50 {
+                let from = rng.gen_range(0, len);
+                let to = rng.gen_range(0, len);
+                let expected = g.shortest_path(from, to);
+
+                let cost = expected.as_ref().map(|p| p.0);
+
+                assert_eq!(g.a_star(from, to, |_, _| 0).map(|p| p.0), cost);
+                let actual = g.a_star(from, to, manhattan);
+                assert_eq!(actual.as_ref().map(|p| p.0), cost);
+                if let Some((_, path)) = actual {
+                    assert_eq!(path.first(), Some(&from));
+                    assert_eq!(path.last(), Some(&to));
+                }
+            }
+        }
+    }
+
+    fn random_graph(nodes: u64, edges_per_node: usize) -> graph::InternetOfThings {
+        let mut g = graph::InternetOfThings::new();
+        let mut rng = thread_rng();
+
+        g.set_nodes((0..nodes).collect());
+        for n in 0..nodes {
+            let edges = (0..edges_per_node)
+                .map(|_| (rng.gen_range(1, 100), rng.gen_range(0, nodes)))
+                .collect();
+            g.set_edges(n, edges).unwrap();
+        }
+        g
+    }
+
+    #[bench]
+    fn bench_graph_shortest_path(b: &mut Bencher) {
+        let nodes = 50_000;
+        let g = random_graph(nodes, 4);
+        let mut rng = thread_rng();
+
+        b.iter(|| {
+            let from = rng.gen_range(0, nodes);
+            let to = rng.gen_range(0, nodes);
+            g.shortest_path(from, to)
+        });
+    }
+
+    #[test]
+    fn graph_minimum_spanning_tree() {
+        let len = 10;
+        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+
+        let g = build_graph(graph::InternetOfThings::new(), &items);
+
+        for (total, tree) in [g.minimum_spanning_tree(), g.minimum_spanning_tree_prim()].iter() {
+            assert_eq!(*total, 9);
+            assert_eq!(tree.len(), len as usize - 1);
+            assert!(tree.iter().all(|e| e.2 == 1));
+
+            let mut covered: Vec<u64> = tree.iter().flat_map(|e| vec![e.0, e.1]).collect();
+            covered.sort();
+            covered.dedup();
+            assert_eq!(covered.len(), len as usize);
+        }
+    }
+
+    #[test]
+    fn graph_minimum_spanning_forest() {
+        let mut g = graph::InternetOfThings::new();
+        g.set_nodes(vec![1, 2, 3, 4, 5, 6]);
+        g.set_edges(1, vec![(4, 2), (1, 3)]).unwrap();
+        g.set_edges(3, vec![(2, 2)]).unwrap();
+        g.set_edges(4, vec![(-1, 5)]).unwrap();
+        g.set_edges(5, vec![(7, 4)]).unwrap();
+
+        assert_eq!(g.minimum_spanning_tree(), (2, vec![(4, 5, -1), (1, 3, 1), (3, 2, 2)]));
+        let (total, mut tree) = g.minimum_spanning_tree_prim();
+        tree.sort_by_key(|e| e.2);
+        assert_eq!(total, 2);
+        assert_eq!(tree, vec![(4, 5, -1), (1, 3, 1), (3, 2, 2)]);
+
+        let mut rng = thread_rng();
+        for _ in 0..20 {
+            let g = random_graph(rng.gen_range(1, 100), rng.gen_range(0, 3));
+            let kruskal = g.minimum_spanning_tree();
+            let prim = g.minimum_spanning_tree_prim();
+            assert_eq!(kruskal.0, prim.0);
+            assert_eq!(kruskal.1.len(), prim.1.len());
+        }
+    }
+
+    #[bench]
+    fn bench_graph_minimum_spanning_tree(b: &mut Bencher) {
+        let g = random_graph(10_000, 4);
+        b.iter(|| g.minimum_spanning_tree());
+    }
+
+    #[bench]
+    fn bench_graph_minimum_spanning_tree_prim(b: &mut Bencher) {
+        let g = random_graph(10_000, 4);
+        b.iter(|| g.minimum_spanning_tree_prim());
+    }
+
+    #[test]
+    fn graph_maximum_flow() {
+        let mut g = graph::InternetOfThings::new();
+        g.set_nodes(vec![0, 1, 2, 3, 4, 5]);
+        g.set_edges(0, vec![(16, 1), (13, 2)]).unwrap();
+        g.set_edges(1, vec![(12, 3)]).unwrap();
+        g.set_edges(2, vec![(4, 1), (14, 4)]).unwrap();
+        g.set_edges(3, vec![(9, 2), (20, 5)]).unwrap();
+        g.set_edges(4, vec![(7, 3), (4, 5)]).unwrap();
+
+        for flow in [g.maximum_flow(0, 5), g.maximum_flow_dinic(0, 5)] {
+            let mut flow = flow.unwrap();
+            assert_eq!(flow.value, 23);
+            flow.min_cut.sort();
+            assert_eq!(flow.min_cut, vec![(1, 3), (4, 3), (4, 5)]);
+            let into_sink: i64 = flow.flows.iter().filter(|f| f.1 == 5).map(|f| f.2).sum();
+            assert_eq!(into_sink, 23);
+        }
+
+        assert_eq!(g.maximum_flow(5, 0).map(|f| f.value), Some(0));
+        assert_eq!(g.maximum_flow(0, 0), None);
+        assert_eq!(g.maximum_flow(0, 100), None);
+        assert_eq!(g.maximum_flow_dinic(100, 0), None);
+    }
+
+    #[test]
+    fn graph_maximum_flow_edmonds_karp_matches_dinic() {
+        let mut rng = thread_rng();
+
+        for _ in 0..50 {
+            let len = rng.gen_range(2, 30);
+            let mut g = graph::InternetOfThings::new();
+            g.set_nodes((0..len).collect());
+            let mut capacities = HashMap::new();
+            for from in 0..len {
+                let edges: Vec<(i32, u64)> = (0..rng.gen_range(0, 5))
+                    .map(|_| (rng.gen_range(0, 20), rng.gen_range(0, len)))
+                    .collect();
+                for (w, to) in edges.iter() {
+                    *capacities.entry((from, *to)).or_insert(0) += *w as i64;
+                }
+                g.set_edges(from, edges).unwrap();
+            }
+
+            let (source, sink) = (0, len - 1);
+            let edmonds_karp = g.maximum_flow(source, sink).unwrap();
+            let dinic = g.maximum_flow_dinic(source, sink).unwrap();
+            assert_eq!(edmonds_karp.value, dinic.value);
+
+            for flow in [edmonds_karp, dinic] {
+                let mut net = vec![0i64; len as usize];
+                let mut used = HashMap::new();
+                for (from, to, f) in flow.flows.iter() {
+                    net[*from as usize] -= f;
+                    net[*to as usize] += f;
+                    *used.entry((*from, *to)).or_insert(0) += f;
+                }
+                for (edge, f) in used {
+                    assert!(f <= capacities[&edge]);
+                }
+                for n in 1..len - 1 {
+                    assert_eq!(net[n as usize], 0);
+                }
+                assert_eq!(net[sink as usize], flow.value);
+
+                let mut cut = flow.min_cut.clone();
+                cut.sort();
+                cut.dedup();
+                let cut: i64 = cut.iter().map(|e| capacities[e]).sum();
+                assert_eq!(cut, flow.value);
+            }
+        }
+    }
+
+    #[bench]
+    fn bench_graph_maximum_flow(b: &mut Bencher) {
+        let g = random_graph(2_000, 4);
+        b.iter(|| g.maximum_flow(0, 1_999));
+    }
+
+    #[bench]
+    fn bench_graph_maximum_flow_dinic(b: &mut Bencher) {
+        let g = random_graph(2_000, 4);
+        b.iter(|| g.maximum_flow_dinic(0, 1_999));
+    }
+
+    #[test]
+    fn graph_k_shortest_paths() {
+        let mut g = graph::InternetOfThings::new();
+        g.set_nodes(vec![1, 2, 3, 4, 5, 6]);
+        g.set_edges(1, vec![(3, 2), (2, 3)]).unwrap();
+        g.set_edges(2, vec![(4, 4)]).unwrap();
+        g.set_edges(3, vec![(1, 2), (2, 4), (3, 5)]).unwrap();
+        g.set_edges(4, vec![(2, 5), (1, 6)]).unwrap();
+        g.set_edges(5, vec![(2, 6)]).unwrap();
+
+        assert_eq!(
+            g.k_shortest_paths(1, 6, 3),
+            vec![
+                (5, vec![1, 3, 4, 6]),
+                (7, vec![1, 3, 5, 6]),
+                (8, vec![1, 2, 4, 6])
+            ]
+        );
+        assert_eq!(g.k_shortest_paths(1, 6, 100).len(), 7);
+        assert_eq!(g.k_shortest_paths(1, 6, 0), vec![]);
+        assert_eq!(g.k_shortest_paths(6, 1, 3), vec![]);
+        assert_eq!(g.k_shortest_paths(1, 100, 3), vec![]);
+        assert_eq!(g.k_shortest_paths(1, 1, 3), vec![(0, vec![1])]);
+    }
+
+    fn simple_path_costs(g: &graph::InternetOfThings, from: u64, to: u64) -> Vec<u64> {
+        fn walk(
+            g: &graph::InternetOfThings,
+            path: &mut Vec<u64>,
+            cost: u64,
+            to: u64,
+            costs: &mut Vec<u64>,
+        ) {
+            let u = *path.last().unwrap();
+            if u == to {
+                costs.push(cost);
+                return;
+            }
+            let mut cheapest: HashMap<u64, u64> = HashMap::new();
+            for (v, w) in g.neighbors(u).unwrap() {
+                let c = cheapest.entry(v).or_insert(w as u64);
+                *c = min(*c, w as u64);
+            }
+            for (v, w) in cheapest {
+                if !path.contains(&v) {
+                    path.push(v);
+                    walk(g, path, cost + w, to, costs);
+                    path.pop();
+                }
+            }
+        }
+        let mut costs = vec![];
+        walk(g, &mut vec![from], 0, to, &mut costs);
+        costs.sort();
+        costs
+    }
+
+    #[test]
+    fn graph_k_shortest_paths_match_brute_force() {
+        let mut rng = thread_rng();
+        for _ in 0..20 {
+            let g = random_graph(9, 3);
+            let (from, to) = (rng.gen_range(0, 9), rng.gen_range(0, 9));
+            let expected = simple_path_costs(&g, from, to);
+
+            let paths = g.k_shortest_paths(from, to, 12);
+            assert_eq!(paths.len(), min(12, expected.len()));
+            for ((cost, path), expected) in paths.iter().zip(&expected) {
+                assert_eq!(cost, expected);
+                assert_eq!(path.first(), Some(&from));
+                assert_eq!(path.last(), Some(&to));
+                assert_eq!(HashSet::<&u64>::from_iter(path).len(), path.len());
+                let walked: u64 = path
+                    .windows(2)
+                    .map(|p| {
+                        g.neighbors(p[0])
+                            .unwrap()
+                            .into_iter()
+                            .filter(|(v, _)| *v == p[1])
+                            .map(|(_, w)| w as u64)
+                            .min()
+                            .expect("Path uses a missing edge")
+                    })
+                    .sum();
+                assert_eq!(walked, *cost);
+            }
+            let unique: HashSet<&Vec<u64>> = paths.iter().map(|(_, p)| p).collect();
+            assert_eq!(unique.len(), paths.len());
+        }
+    }
+
+    fn assert_disjoint_paths(g: &graph::InternetOfThings, from: u64, to: u64, count: usize) {
+        let paths = g.disjoint_paths(from, to);
+        assert_eq!(paths.len(), count);
+        // parallel edges may each carry a path of their own
+        let mut used = HashMap::new();
+        for path in paths {
+            assert_eq!(path.first(), Some(&from));
+            assert_eq!(path.last(), Some(&to));
+            for p in path.windows(2) {
+                let neighbors = g.neighbors(p[0]).unwrap();
+                let parallel = neighbors.iter().filter(|(v, _)| *v == p[1]).count();
+                let edge = if g.is_directed() {
+                    (p[0], p[1])
+                } else {
+                    (min(p[0], p[1]), p[0].max(p[1]))
+                };
+                let count = used.entry(edge).or_insert(0);
+                *count += 1;
+                assert!(*count <= parallel, "{:?} is used too often", edge);
+            }
+        }
+    }
+
+    #[test]
+    fn graph_disjoint_paths() {
+        let mut g = graph::InternetOfThings::new_undirected();
+        g.set_nodes(vec![1, 2, 3, 4, 5]);
+        for (a, b) in [(1, 2), (1, 3), (2, 3), (2, 4), (3, 4), (4, 5)] {
+            g.add_edge(a, b, 10).unwrap();
+        }
+        assert_disjoint_paths(&g, 1, 4, 2);
+        assert_disjoint_paths(&g, 2, 3, 3);
+        assert_disjoint_paths(&g, 1, 5, 1);
+        assert_eq!(g.disjoint_paths(1, 1), Vec::<Vec<u64>>::new());
+        assert_eq!(g.disjoint_paths(1, 100), Vec::<Vec<u64>>::new());
+
+        let mut g = graph::InternetOfThings::new();
+        g.set_nodes(vec![1, 2, 3, 4, 5, 6]);
+        g.set_edges(1, vec![(1, 2), (10, 4), (1, 5), (2, 6)]).unwrap();
+        g.set_edges(2, vec![(1, 3)]).unwrap();
+        g.set_edges(3, vec![(1, 4)]).unwrap();
+        g.set_edges(5, vec![(2, 4)]).unwrap();
+        g.set_edges(6, vec![(1, 4)]).unwrap();
+        // cheapest first, then fewest hops
+        assert_eq!(
+            g.disjoint_paths(1, 4),
+            vec![vec![1, 5, 4], vec![1, 6, 4], vec![1, 2, 3, 4], vec![1, 4]]
+        );
+
+        for _ in 0..20 {
+            let g = random_graph(30, 3);
+            let mut unit = graph::InternetOfThings::new();
+            unit.set_nodes((0..30).collect());
+            for n in 0..30 {
+                let edges = g.neighbors(n).unwrap().into_iter().map(|(v, _)| (1, v));
+                unit.set_edges(n, edges.collect()).unwrap();
+            }
+            let flow = unit.maximum_flow(0, 29).unwrap();
+            assert_disjoint_paths(&g, 0, 29, flow.value as usize);
+        }
+    }
+
+    #[bench]
+    fn bench_graph_k_shortest_paths(b: &mut Bencher) {
+        let nodes = 5_000;
+        let g = random_graph(nodes, 4);
+        let mut rng = thread_rng();
+
+        b.iter(|| g.k_shortest_paths(rng.gen_range(0, nodes), rng.gen_range(0, nodes), 5));
+    }
+
+    #[test]
+    fn graph_topological_order() {
+        let mut g = graph::InternetOfThings::new();
+        g.set_nodes(vec![1, 2, 3, 4, 5, 6]);
+        g.set_edges(1, vec![(1, 2), (1, 3)]).unwrap();
+        g.set_edges(2, vec![(1, 4)]).unwrap();
+        g.set_edges(3, vec![(1, 4)]).unwrap();
+        g.set_edges(4, vec![(1, 5)]).unwrap();
+
+        assert_eq!(
+            g.topological_layers(),
+            Ok(vec![vec![1, 6], vec![2, 3], vec![4], vec![5]])
+        );
+        assert_eq!(g.topological_order(), Ok(vec![1, 6, 2, 3, 4, 5]));
+
+        g.set_edges(5, vec![(1, 2)]).unwrap();
+        let cycle = g.topological_order().unwrap_err();
+        let start = cycle.0.iter().position(|n| *n == 2).unwrap();
+        let rotated: Vec<u64> = cycle.0.iter().cycle().skip(start).take(3).cloned().collect();
+        assert_eq!(rotated, vec![2, 4, 5]);
+        assert_eq!(cycle.0.len(), 3);
+        assert!(g.topological_layers().is_err());
+
+        g.set_edges(5, vec![(1, 5)]).unwrap();
+        assert_eq!(g.topological_order(), Err(graph::Cycle(vec![5])));
+    }
+
+    #[test]
+    fn graph_topological_order_is_valid() {
+        let mut rng = thread_rng();
+
+        for _ in 0..20 {
+            let len = rng.gen_range(1, 50);
+            let mut g = graph::InternetOfThings::new();
+            g.set_nodes((0..len).collect());
+            // edges only point to higher ids
+            let mut edges = vec![];
+            for from in 0..len {
+                let out: Vec<(i32, u64)> = (0..rng.gen_range(0, 4))
+                    .filter(|_| from + 1 < len)
+                    .map(|_| (1, rng.gen_range(from + 1, len)))
+                    .collect();
+                edges.extend(out.iter().map(|e| (from, e.1)));
+                g.set_edges(from, out).unwrap();
+            }
+
+            let layers = g.topological_layers().unwrap();
+            let mut position = HashMap::new();
+            for (i, layer) in layers.iter().enumerate() {
+                for n in layer {
+                    position.insert(*n, i);
+                }
+            }
+            assert_eq!(position.len(), len as usize);
+            for (from, to) in edges.iter() {
+                assert!(position[from] < position[to]);
+            }
+
+            let order = g.topological_order().unwrap();
+            assert_eq!(order, layers.into_iter().flatten().collect::<Vec<u64>>());
+
+            if !edges.is_empty() {
+                let (from, to) = edges[rng.gen_range(0, edges.len())];
+                g.set_edges(to, vec![(1, from)]).unwrap();
+                let cycle = g.topological_order().unwrap_err().0;
+                assert!(!cycle.is_empty());
+                assert!(g.strongly_connected_components().iter().any(|c| {
+                    c.len() > 1 && cycle.iter().all(|n| c.contains(n))
+                }));
+            }
+        }
+    }
+
+    #[test]
+    fn graph_mutations() {
+        use crate::graph::GraphError;
+
+        let mut g = graph::InternetOfThings::new();
+        assert!(g.is_directed());
+        assert_eq!(g.add_node(1), Ok(()));
+        assert_eq!(g.add_node(2), Ok(()));
+        assert_eq!(g.add_node(3), Ok(()));
+        assert_eq!(g.add_node(1), Err(GraphError::DuplicateNode(1)));
+
+        assert_eq!(g.add_edge(1, 2, 5), Ok(()));
+        assert_eq!(g.add_edge(2, 3, 1), Ok(()));
+        assert_eq!(g.add_edge(1, 3, 10), Ok(()));
+        assert_eq!(g.add_edge(1, 2, 1), Err(GraphError::DuplicateEdge(1, 2)));
+        assert_eq!(g.add_edge(1, 4, 1), Err(GraphError::UnknownNode(4)));
+        assert_eq!(g.add_edge(4, 1, 1), Err(GraphError::UnknownNode(4)));
+        assert_eq!(g.edges(), 3);
+        assert_eq!(g.neighbors(1), Some(vec![(2, 5), (3, 10)]));
+        assert_eq!(g.neighbors(4), None);
+        assert_eq!(g.shortest_path(1, 3), Some((6, vec![1, 2, 3])));
+
+        assert_eq!(g.update_weight(1, 3, -1), Ok(()));
+        assert!(g.has_negative_weights());
+        assert_eq!(g.update_weight(3, 1, 1), Err(GraphError::UnknownEdge(3, 1)));
+        assert_eq!(g.update_weight(1, 3, 2), Ok(()));
+        assert!(!g.has_negative_weights());
+        assert_eq!(g.shortest_path(1, 3), Some((2, vec![1, 3])));
+
+        assert_eq!(g.remove_edge(1, 3), Ok(()));
+        assert_eq!(g.remove_edge(1, 3), Err(GraphError::UnknownEdge(1, 3)));
+        assert_eq!(g.shortest_path(1, 3), Some((6, vec![1, 2, 3])));
+
+        assert_eq!(g.set_edges(3, vec![(1, 1), (1, 5)]), Err(GraphError::UnknownNode(5)));
+        assert_eq!(g.neighbors(3), Some(vec![]));
+        // parallel edges stay, unlike with `add_edge`
+        assert_eq!(g.set_edges(3, vec![(1, 1), (3, 1)]), Ok(()));
+        assert_eq!(g.neighbors(3), Some(vec![(1, 1), (1, 3)]));
+        assert_eq!(g.shortest_path(3, 1), Some((1, vec![3, 1])));
+        assert_eq!(g.add_edge(3, 1, 2), Err(GraphError::DuplicateEdge(3, 1)));
+        assert_eq!(g.set_edges(3, vec![]), Ok(()));
+        assert_eq!(g.set_edges(5, vec![(1, 5), (-2, 1)]), Ok(()));
+        assert_eq!(g.neighbors(5), Some(vec![(5, 1), (1, -2)]));
+        assert!(g.has_negative_weights());
+
+        assert_eq!(g.remove_node(2), Ok(()));
+        assert_eq!(g.remove_node(2), Err(GraphError::UnknownNode(2)));
+        assert!(g.has_negative_weights());
+        assert_eq!(g.nodes(), 3);
+        assert_eq!(g.neighbors(1), Some(vec![]));
+        assert_eq!(g.neighbors(5), Some(vec![(5, 1), (1, -2)]));
+        assert_eq!(g.remove_node(5), Ok(()));
+        assert!(!g.has_negative_weights());
+        assert_eq!(g.edges(), 0);
+        assert_eq!(g.add_edge(3, 1, 1), Ok(()));
+        assert_eq!(g.shortest_path(3, 1), Some((1, vec![3, 1])));
+    }
+
+    #[test]
+    fn graph_undirected_mutations() {
+        let mut g = graph::InternetOfThings::new_undirected();
+        assert!(!g.is_directed());
+        g.set_nodes(vec![1, 2, 3]);
+
+        g.add_edge(1, 2, 5).unwrap();
+        g.add_edge(2, 3, 1).unwrap();
+        g.add_edge(3, 3, 1).unwrap();
+        assert_eq!(g.edges(), 3);
+        assert_eq!(g.neighbors(2), Some(vec![(1, 5), (3, 1)]));
+        assert_eq!(g.shortest_path(3, 1), Some((6, vec![3, 2, 1])));
+
+        g.update_weight(2, 1, 2).unwrap();
+        assert_eq!(g.neighbors(1), Some(vec![(2, 2)]));
+
+        g.set_edges(2, vec![(7, 1)]).unwrap();
+        assert_eq!(g.neighbors(3), Some(vec![(3, 1)]));
+        assert_eq!(g.neighbors(1), Some(vec![(2, 7)]));
+        assert_eq!(g.edges(), 2);
+
+        g.remove_edge(1, 2).unwrap();
+        assert_eq!(g.neighbors(2), Some(vec![]));
+        assert_eq!(g.edges(), 1);
+    }
+
+    #[test]
+    fn graph_mutations_match_model() {
+        let mut rng = thread_rng();
+
+        for directed in [true, false] {
+            let mut g = if directed {
+                graph::InternetOfThings::new()
+            } else {
+                graph::InternetOfThings::new_undirected()
+            };
+            let mut nodes: HashSet<u64> = HashSet::new();
+            let mut edges: HashMap<(u64, u64), i32> = HashMap::new();
+
+            for _ in 0..2_000 {
+                let (a, b) = (rng.gen_range(0, 20), rng.gen_range(0, 20));
+                let w = rng.gen_range(-5, 20);
+                let key = if directed || a <= b { (a, b) } else { (b, a) };
+                match rng.gen_range(0, 5) {
+                    0 => assert_eq!(g.add_node(a).is_ok(), nodes.insert(a)),
+                    1 => {
+                        assert_eq!(g.remove_node(a).is_ok(), nodes.remove(&a));
+                        edges.retain(|e, _| e.0 != a && e.1 != a);
+                    }
+                    2 => {
+                        let ok = nodes.contains(&a) && nodes.contains(&b) && !edges.contains_key(&key);
+                        assert_eq!(g.add_edge(a, b, w).is_ok(), ok);
+                        if ok {
+                            edges.insert(key, w);
+                        }
+                    }
+                    3 => assert_eq!(g.remove_edge(a, b).is_ok(), edges.remove(&key).is_some()),
+                    _ => {
+                        let ok = edges.contains_key(&key);
+                        assert_eq!(g.update_weight(a, b, w).is_ok(), ok);
+                        if ok {
+                            edges.insert(key, w);
+                        }
+                    }
+                }
+
+                assert_eq!(g.nodes(), nodes.len());
+                assert_eq!(g.edges(), edges.len() as u64);
+                assert_eq!(g.has_negative_weights(), edges.values().any(|w| *w < 0));
+            }
+
+            for n in nodes.iter() {
+                let mut actual = g.neighbors(*n).unwrap();
+                actual.sort();
+                let mut expected: Vec<(u64, i32)> = edges
+                    .iter()
+                    .filter_map(|(e, w)| {
+                        if e.0 == *n {
+                            Some((e.1, *w))
+                        } else if !directed && e.1 == *n {
+                            Some((e.0, *w))
+                        } else {
+                            None
+                        }
+                    })
+                    .collect();
+                expected.sort();
+                assert_eq!(actual, expected);
+            }
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Link {
+        latency_ms: f64,
+        bandwidth: u32,
+    }
+
+    fn link(latency_ms: f64) -> Link {
+        Link {
+            latency_ms,
+            bandwidth: 100,
+        }
+    }
+
+    #[test]
+    fn generic_graph_payloads() {
+        use crate::graph::GraphError;
+
+        let mut g = generic_graph::Graph::new();
+        for name in ["gateway", "hub", "sensor-a", "sensor-b"].iter() {
+            g.add_node(name.to_string()).unwrap();
+        }
+        let n = |s: &str| s.to_string();
+        assert_eq!(g.add_node(n("hub")), Err(GraphError::DuplicateNode(n("hub"))));
+
+        g.add_edge(n("gateway"), n("hub"), link(1.5)).unwrap();
+        g.add_edge(n("hub"), n("sensor-a"), link(0.25)).unwrap();
+        g.add_edge(n("gateway"), n("sensor-a"), link(2.0)).unwrap();
+        g.add_edge(n("sensor-a"), n("sensor-b"), link(0.5)).unwrap();
+        assert_eq!(
+            g.add_edge(n("hub"), n("unknown"), link(1.0)),
+            Err(GraphError::UnknownNode(n("unknown")))
+        );
+        assert_eq!(g.nodes(), 4);
+        assert_eq!(g.edges(), 4);
+        assert_eq!(g.edge(&n("hub"), &n("sensor-a")), Some(&link(0.25)));
+        assert_eq!(g.neighbors(&n("sensor-b")), Some(vec![]));
+
+        // the same search over latency and hop count
+        assert_eq!(
+            g.shortest_path(&n("gateway"), &n("sensor-b"), |l| l.latency_ms),
+            Some((2.25, vec![n("gateway"), n("hub"), n("sensor-a"), n("sensor-b")]))
+        );
+        assert_eq!(
+            g.shortest_path(&n("gateway"), &n("sensor-b"), |_| 1),
+            Some((2, vec![n("gateway"), n("sensor-a"), n("sensor-b")]))
+        );
+        assert_eq!(g.shortest_path(&n("sensor-b"), &n("gateway"), |_| 1), None);
+        assert_eq!(
+            g.shortest_paths_from(&n("hub"), |l| l.bandwidth).map(|p| p.len()),
+            Some(3)
+        );
+
+        let (total, tree) = g.minimum_spanning_tree(|l| l.latency_ms);
+        assert_eq!(total, 2.25);
+        assert_eq!(tree.len(), 3);
+        assert_eq!(g.minimum_spanning_tree(|_| 1).0, 3);
+    }
+
+    #[test]
+    fn generic_graph_matches_internet_of_things() {
+        let mut rng = thread_rng();
+
+        for _ in 0..20 {
+            let len = rng.gen_range(1, 40);
+            let mut g = graph::InternetOfThings::new();
+            let mut generic = generic_graph::Graph::new();
+            g.set_nodes((0..len).collect());
+            for n in 0..len {
+                generic.add_node(n).unwrap();
+            }
+            for from in 0..len {
+                let mut edges = vec![];
+                for _ in 0..rng.gen_range(0, 4) {
+                    let (w, to) = (rng.gen_range(0, 20), rng.gen_range(0, len));
+                    if generic.add_edge(from, to, w).is_ok() {
+                        edges.push((w, to));
+                    }
+                }
+                g.set_edges(from, edges).unwrap();
+            }
+
+            for from in 0..len {
+                let expected = g.shortest_paths_from(from).unwrap();
+                let actual = generic.shortest_paths_from(&from, |w| *w as u64).unwrap();
+                assert_eq!(actual.len(), expected.len());
+                for (to, (cost, _)) in expected {
+                    assert_eq!(actual[&to].0 as i64, cost);
+                }
+            }
+            assert_eq!(
+                generic.minimum_spanning_tree(|w| *w as i64).0,
+                g.minimum_spanning_tree().0
+            );
+        }
+    }
+
+    #[test]
+    fn csr_graph_matches_adjacency_list() {
+        let len = 10;
+        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+
+        let g = build_graph(graph::InternetOfThings::new(), &items);
+        let csr = csr_graph::CsrGraph::freeze(&g);
+
+        assert_eq!(csr.nodes(), g.nodes());
+        assert_eq!(csr.edges(), g.edges());
+        let neighbors: Vec<(u64, i32)> = csr
+            .neighbors(items[0].numerical_id)
+            .unwrap()
+            .iter()
+            .map(|e| (csr.node_key(e.target()), e.weight()))
+            .collect();
+        assert_eq!(g.neighbors(items[0].numerical_id), Some(neighbors));
+        assert!(csr.neighbors(100).is_none());
+
+        assert_eq!(
+            csr.shortest_path(items[0].numerical_id, items[9].numerical_id),
+            g.shortest_path(items[0].numerical_id, items[9].numerical_id)
+        );
+        assert_eq!(
+            csr.connected(items[0].numerical_id, 2),
+            g.connected(items[0].numerical_id, 2)
+        );
+
+        let mut rng = thread_rng();
+        let g = random_graph(500, 3);
+        let csr = csr_graph::CsrGraph::freeze(&g);
+        for _ in 0..50 {
+            let from = rng.gen_range(0, 500);
+            let to = rng.gen_range(0, 500);
+            assert_eq!(csr.shortest_path(from, to), g.shortest_path(from, to));
+            assert_eq!(csr.connected(from, 3), g.connected(from, 3));
+        }
+        assert_eq!(csr.shortest_paths_from(0), g.shortest_paths_from(0));
+    }
+
+    #[test]
+    fn csr_graph_counts_undirected_edges_once() {
+        let mut g = graph::InternetOfThings::new_undirected();
+        g.set_nodes(vec![1, 2, 3, 4]);
+        g.add_edge(1, 2, 1).unwrap();
+        g.add_edge(2, 3, 1).unwrap();
+        g.add_edge(3, 1, 1).unwrap();
+        g.add_edge(4, 4, 1).unwrap();
+
+        let csr = csr_graph::CsrGraph::freeze(&g);
+        assert!(!csr.is_directed());
+        assert_eq!(csr.edges(), g.edges());
+        assert_eq!(csr.edges(), 4);
+    }
+
+    #[bench]
+    fn bench_csr_graph_shortest_path(b: &mut Bencher) {
+        let nodes = 50_000;
+        let g = csr_graph::CsrGraph::freeze(&random_graph(nodes, 4));
+        let mut rng = thread_rng();
+
+        b.iter(|| {
+            let from = rng.gen_range(0, nodes);
+            let to = rng.gen_range(0, nodes);
+            g.shortest_path(from, to)
+        });
+    }
+
+    #[bench]
+    fn bench_graph_connected(b: &mut Bencher) {
+        let nodes = 50_000;
+        let g = random_graph(nodes, 4);
+        let mut rng = thread_rng();
+
+        b.iter(|| g.connected(rng.gen_range(0, nodes), 4));
+    }
+
+    #[bench]
+    fn bench_csr_graph_connected(b: &mut Bencher) {
+        let nodes = 50_000;
+        let g = csr_graph::CsrGraph::freeze(&random_graph(nodes, 4));
+        let mut rng = thread_rng();
+
+        b.iter(|| g.connected(rng.gen_range(0, nodes), 4));
+    }
+
+    fn assert_same_graph(a: &graph::InternetOfThings, b: &graph::InternetOfThings) {
+        assert_eq!(a.is_directed(), b.is_directed());
+        assert_eq!(a.nodes(), b.nodes());
+        assert_eq!(a.edges(), b.edges());
+        for i in 0..a.node_count() {
+            let node = a.node_key(i);
+            let mut expected = a.neighbors(node).unwrap();
+            let mut actual = b.neighbors(node).expect("Node is missing");
+            expected.sort();
+            actual.sort();
+            assert_eq!(expected, actual);
+        }
+    }
+
+    fn io_test_graphs() -> Vec<graph::InternetOfThings> {
+        let mut directed = random_graph(100, 3);
+        directed.add_node(1000).unwrap();
+        // random_graph may have picked this one already
+        directed.insert_edge(5, 5, 7).unwrap();
+        directed.update_weight(5, 5, -7).unwrap();
+
+        let mut undirected = graph::InternetOfThings::new_undirected();
+        undirected.set_nodes(vec![1, 2, 3, 4, 5]);
+        undirected.add_edge(1, 2, 4).unwrap();
+        undirected.add_edge(2, 3, -1).unwrap();
+        undirected.add_edge(3, 3, 2).unwrap();
+        undirected.add_edge(3, 1, 9).unwrap();
+
+        vec![directed, undirected, graph::InternetOfThings::new()]
+    }
+
+    #[test]
+    fn graph_io_round_trips() {
+        for g in io_test_graphs() {
+            assert_same_graph(&g, &graph_io::from_dot(&graph_io::to_dot(&g)).unwrap());
+            assert_same_graph(
+                &g,
+                &graph_io::from_edge_list(&graph_io::to_edge_list(&g), g.is_directed()).unwrap(),
+            );
+            assert_same_graph(&g, &graph_io::from_graphml(&graph_io::to_graphml(&g)).unwrap());
+        }
+    }
+
+    #[test]
+    fn graph_io_dot() {
+        let g = graph_io::from_dot(
+            "// handwritten\n\
+             graph network {\n\
+             \x20   node [shape=box];\n\
+             \x20   7;\n\
+             \x20   1 -- 2 -- \"3\" [weight=5, label=\"uplink\"];\n\
+             \x20   3 -- 4;\n\
+             }\n",
+        )
+        .unwrap();
+        assert!(!g.is_directed());
+        assert_eq!(g.nodes(), 5);
+        assert_eq!(g.neighbors(2), Some(vec![(1, 5), (3, 5)]));
+        assert_eq!(g.neighbors(4), Some(vec![(3, 1)]));
+
+        let error = |input: &str| graph_io::from_dot(input).err().expect("Input is invalid");
+        assert_eq!(error("digraph {\n1 -> x;\n}").line(), 2);
+        assert_eq!(error("digraph {\n1 -- 2;\n}").line(), 2);
+        assert_eq!(error("digraph {\n1 -> 2 [weight=a];\n}").line(), 2);
+        assert_eq!(error("tree {\n}").line(), 1);
+        assert_eq!(error("digraph {\n1 -> 2;\n").line(), 2);
+        assert_eq!(
+            graph_io::from_dot("digraph {\n1 -> 2;\n1 -> 2;\n}").unwrap().edges(),
+            2
+        );
+    }
+
+    #[test]
+    fn graph_io_edge_list() {
+        let g = graph_io::from_edge_list("1,2,3\n\n2, 1, -4\n9,,\n", true).unwrap();
+        assert_eq!(g.nodes(), 3);
+        assert_eq!(g.neighbors(2), Some(vec![(1, -4)]));
+        assert_eq!(g.neighbors(9), Some(vec![]));
+
+        let error = |input: &str| graph_io::from_edge_list(input, false).err().expect("Input is invalid");
+        assert_eq!(error("from,to,weight\n1,2\n").line(), 2);
+        assert_eq!(error("from,to,weight\n1,2,3\n1,,3\n").line(), 3);
+        assert_eq!(error("1,2,3\n3,-2,1\n").line(), 2);
+    }
+
+    #[test]
+    fn graph_io_graphml() {
+        let g = graph_io::from_graphml(
+            "<?xml version=\"1.0\"?>\n\
+             <graphml>\n\
+             <key id=\"d0\" for=\"edge\" attr.name=\"weight\" attr.type=\"int\"/>\n\
+             <graph edgedefault='directed'>\n\
+             <!-- edges before nodes are fine -->\n\
+             <edge source=\"1\" target=\"2\">\n\
+             <data key=\"d0\">12</data>\n\
+             </edge>\n\
+             <edge source=\"2\" target=\"1\"/>\n\
+             <node id=\"1\"/><node id=\"2\"></node>\n\
+             </graph>\n\
+             </graphml>\n",
+        )
+        .unwrap();
+        assert!(g.is_directed());
+        assert_eq!(g.neighbors(1), Some(vec![(2, 12)]));
+        assert_eq!(g.neighbors(2), Some(vec![(1, 1)]));
+
+        let error = |input: &str| graph_io::from_graphml(input).err().expect("Input is invalid");
+        assert_eq!(error("<graphml>\n<graph>\n<node/>\n</graph></graphml>").line(), 3);
+        assert_eq!(error("<graphml>\n<node id=\"1\"/>\n</graphml>").line(), 2);
+        assert_eq!(error("<graphml>\n<graph>\n\n<node id=\"1\"").line(), 4);
+        assert_eq!(
+            error("<graph>\n<edge source=\"1\" target=\"2\">\n<data key=\"weight\">x</data>")
+                .line(),
+            3
+        );
+        assert_eq!(
+            error("<graph>\n<node id=\"1\"/>\n<edge source=\"1\" target=\"2\"/>\n</graph>"),
+            graph_io::ParseError::Graph(3, graph::GraphError::UnknownNode(2))
+        );
+        assert_eq!(
+            error("<graph>\n<node id=\"1\"/>\n<node id=\"1\"/>\n</graph>"),
+            graph_io::ParseError::Graph(3, graph::GraphError::DuplicateNode(1))
+        );
+
+        let unknown = error("<graph>\n<node id=\"1\"/>\n<edge source=\"1\" target=\"2\"/>");
+        assert_eq!(unknown.to_string(), "line 3: unknown node 2");
+        let source = std::error::Error::source(&unknown).expect("Graph errors have a source");
+        assert_eq!(source.to_string(), "unknown node 2");
+        assert!(error("<graph>\n<node/>").to_string().starts_with("line 2: "));
+    }
+
     #[test]
     fn graph_neighbors() {
         let len = 10;
@@ -717,4 +2182,204 @@ mod tests {
             ))
         )
     }
+
+    #[test]
+    fn graph_neighbors_on_cycles() {
+        let len = 10;
+        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+
+        let g = build_graph(graph::InternetOfThings::new(), &items);
+        assert_eq!(
+            g.connected(items[0].numerical_id, 2),
+            Some(HashSet::from_iter(vec![0, 1, 2, 3, 4, 6, 7, 8, 9].into_iter()))
+        );
+        assert_eq!(g.connected(100, 2), None);
+
+        let len = 50;
+        let mut g = graph::InternetOfThings::new();
+        g.set_nodes((0..len).collect());
+        for n in 0..len {
+            g.set_edges(n, (0..len).filter(|m| *m != n).map(|m| (1, m)).collect()).unwrap();
+        }
+        assert_eq!(g.connected(0, 1).map(|c| c.len()), Some(len as usize - 1));
+        assert_eq!(g.connected(0, 40).map(|c| c.len()), Some(len as usize));
+    }
+
+    #[test]
+    fn graph_traversals() {
+        let len = 10;
+        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+
+        let g = build_graph(graph::InternetOfThings::new(), &items);
+
+        assert_eq!(
+            g.bfs(items[0].numerical_id).collect::<Vec<u64>>(),
+            vec![0, 1, 2, 3, 9, 4, 8, 7, 6, 5]
+        );
+        assert_eq!(
+            g.dfs(items[0].numerical_id).collect::<Vec<u64>>(),
+            vec![0, 1, 2, 3, 4, 5, 6, 9, 8, 7]
+        );
+        assert_eq!(g.bfs(items[7].numerical_id).count(), len as usize);
+        assert_eq!(g.bfs(100).count(), 0);
+        assert_eq!(g.dfs(100).count(), 0);
+    }
+
+    fn sorted_components(mut components: Vec<Vec<u64>>) -> Vec<Vec<u64>> {
+        for c in components.iter_mut() {
+            c.sort();
+        }
+        components.sort();
+        components
+    }
+
+    #[test]
+    fn graph_components() {
+        let len = 10;
+        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+
+        let mut g = build_graph(graph::InternetOfThings::new(), &items);
+        g.set_edges(100, vec![]).unwrap();
+        g.set_edges(101, vec![(1, 100)]).unwrap();
+
+        assert_eq!(
+            sorted_components(g.components()),
+            vec![(0..len).collect(), vec![100, 101]]
+        );
+        assert_eq!(g.strongly_connected_components().len(), 3);
+
+        let mut g = graph::InternetOfThings::new();
+        g.set_nodes(vec![1, 2, 3, 4, 5, 6]);
+        g.set_edges(1, vec![(1, 2)]).unwrap();
+        g.set_edges(2, vec![(1, 3)]).unwrap();
+        g.set_edges(3, vec![(1, 1), (1, 4)]).unwrap();
+        g.set_edges(4, vec![(1, 5)]).unwrap();
+        g.set_edges(5, vec![(1, 4)]).unwrap();
+
+        let scc = g.strongly_connected_components();
+        // reverse topological order
+        assert_eq!(sorted_components(vec![scc[0].clone()]), vec![vec![4, 5]]);
+        assert_eq!(
+            sorted_components(scc),
+            vec![vec![1, 2, 3], vec![4, 5], vec![6]]
+        );
+        assert_eq!(
+            sorted_components(g.components()),
+            vec![vec![1, 2, 3, 4, 5], vec![6]]
+        );
+    }
+
+    #[test]
+    fn graph_bridges_and_articulation_points() {
+        let len = 10;
+        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+
+        let g = build_graph(graph::InternetOfThings::new(), &items);
+
+        let mut bridges: Vec<(u64, u64)> = g
+            .bridges()
+            .into_iter()
+            .map(|(a, b)| (min(a, b), a.max(b)))
+            .collect();
+        bridges.sort();
+        assert_eq!(bridges, vec![(0, 1), (0, 2), (7, 9), (8, 9)]);
+
+        let mut points = g.articulation_points();
+        points.sort();
+        assert_eq!(points, vec![0, 9]);
+    }
+
+    #[test]
+    fn graph_bridges_with_parallel_edges() {
+        let mut g = graph::InternetOfThings::new();
+        g.set_nodes(vec![1, 2, 3]);
+        g.insert_edge(1, 2, 1).unwrap();
+        g.insert_edge(1, 2, 1).unwrap();
+        g.insert_edge(2, 3, 1).unwrap();
+        g.insert_edge(3, 2, 1).unwrap();
+        assert_eq!(g.bridges(), vec![(2, 3)]);
+        assert_eq!(g.articulation_points(), vec![2]);
+
+        let mut g = graph::InternetOfThings::new_undirected();
+        g.set_nodes(vec![1, 2, 3]);
+        g.insert_edge(1, 2, 1).unwrap();
+        g.insert_edge(1, 2, 5).unwrap();
+        g.insert_edge(2, 3, 1).unwrap();
+        assert_eq!(g.bridges(), vec![(2, 3)]);
+
+        g.remove_edge(1, 2).unwrap();
+        g.insert_edge(1, 2, 1).unwrap();
+        let mut bridges = g.bridges();
+        bridges.sort();
+        assert_eq!(bridges, vec![(1, 2), (2, 3)]);
+    }
+
+    fn graph_from_edges(nodes: &[u64], edges: &[(u64, u64)]) -> graph::InternetOfThings {
+        let mut g = graph::InternetOfThings::new();
+        g.set_nodes(nodes.to_vec());
+        for n in nodes {
+            g.set_edges(*n, edges.iter().filter(|e| e.0 == *n).map(|e| (1, e.1)).collect()).unwrap();
+        }
+        g
+    }
+
+    #[test]
+    fn graph_bridges_and_articulation_points_brute_force() {
+        let mut rng = thread_rng();
+
+        for _ in 0..50 {
+            let len = rng.gen_range(1, 15);
+            let nodes: Vec<u64> = (0..len).collect();
+            let edges: Vec<(u64, u64)> = (0..rng.gen_range(0, 2 * len))
+                .map(|_| (rng.gen_range(0, len), rng.gen_range(0, len)))
+                .collect();
+            let g = graph_from_edges(&nodes, &edges);
+            let components = g.components().len();
+
+            let mut expected_bridges = vec![];
+            for (a, b) in edges.iter().filter(|e| e.0 != e.1) {
+                let link = (min(*a, *b), *a.max(b));
+                // opposite edges make one link, any more keep it up
+                let count = |from, to| edges.iter().filter(|e| **e == (from, to)).count();
+                if count(*a, *b).max(count(*b, *a)) > 1 {
+                    continue;
+                }
+                let without: Vec<(u64, u64)> = edges
+                    .iter()
+                    .filter(|e| (min(e.0, e.1), e.0.max(e.1)) != link)
+                    .cloned()
+                    .collect();
+                if graph_from_edges(&nodes, &without).components().len() > components {
+                    expected_bridges.push(link);
+                }
+            }
+            expected_bridges.sort();
+            expected_bridges.dedup();
+
+            let mut bridges: Vec<(u64, u64)> = g
+                .bridges()
+                .into_iter()
+                .map(|(a, b)| (min(a, b), a.max(b)))
+                .collect();
+            bridges.sort();
+            assert_eq!(bridges, expected_bridges);
+
+            let expected_points: Vec<u64> = nodes
+                .iter()
+                .filter(|n| {
+                    let rest: Vec<u64> = nodes.iter().filter(|m| m != n).cloned().collect();
+                    let without: Vec<(u64, u64)> = edges
+                        .iter()
+                        .filter(|e| e.0 != **n && e.1 != **n)
+                        .cloned()
+                        .collect();
+                    graph_from_edges(&rest, &without).components().len() > components
+                })
+                .cloned()
+                .collect();
+            let mut points = g.articulation_points();
+            points.sort();
+            assert_eq!(points, expected_points);
+        }
+    }
 }
@@ -0,0 +1,202 @@
+use crate::heap::{Handle, PriorityQueue};
+use crate::MessageNotification;
+
+struct Node {
+    notification: MessageNotification,
+    child: Option<usize>,
+    sibling: Option<usize>,
+    // the parent for a first child, otherwise the previous sibling
+    prev: Option<usize>,
+}
+
+pub struct PairingHeap {
+    pub length: usize,
+    root: Option<usize>,
+    nodes: Vec<Option<Node>>,
+    // slots of popped nodes, to be handed out again
+    free: Vec<usize>,
+}
+
+impl PairingHeap {
+    pub fn new_empty() -> PairingHeap {
+        PairingHeap {
+            length: 0,
+            root: None,
+            nodes: vec![],
+            free: vec![],
+        }
+    }
+
+    fn allocate(&mut self, node: Node) -> usize {
+        match self.free.pop() {
+            Some(i) => {
+                self.nodes[i] = Some(node);
+                i
+            }
+            None => {
+                self.nodes.push(Some(node));
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    fn node(&self, i: usize) -> &Node {
+        self.nodes[i].as_ref().expect("Node was already removed")
+    }
+
+    fn node_mut(&mut self, i: usize) -> &mut Node {
+        self.nodes[i].as_mut().expect("Node was already removed")
+    }
+
+    fn meld(&mut self, a: Option<usize>, b: Option<usize>) -> Option<usize> {
+        match (a, b) {
+            (Some(a), Some(b)) => {
+                let (parent, child) = if self.node(a).notification.no_messages
+                    >= self.node(b).notification.no_messages
+                {
+                    (a, b)
+                } else {
+                    (b, a)
+                };
+                let first = self.node(parent).child;
+                if let Some(first) = first {
+                    self.node_mut(first).prev = Some(child);
+                }
+                {
+                    let c = self.node_mut(child);
+                    c.sibling = first;
+                    c.prev = Some(parent);
+                }
+                self.node_mut(parent).child = Some(child);
+                Some(parent)
+            }
+            (a, None) => a,
+            (None, b) => b,
+        }
+    }
+
+    fn detach(&mut self, i: usize) {
+        let node = self.node_mut(i);
+        node.sibling = None;
+        node.prev = None;
+    }
+
+    fn merge_pairs(&mut self, first: Option<usize>) -> Option<usize> {
+        let mut pairs = vec![];
+        let mut current = first;
+        while let Some(a) = current {
+            let b = self.node(a).sibling;
+            current = b.and_then(|b| self.node(b).sibling);
+            self.detach(a);
+            if let Some(b) = b {
+                self.detach(b);
+            }
+            pairs.push(self.meld(Some(a), b));
+        }
+        pairs
+            .into_iter()
+            .rev()
+            .fold(None, |acc, p| self.meld(acc, p))
+    }
+
+    fn cut(&mut self, i: usize) {
+        let (prev, sibling) = {
+            let node = self.node(i);
+            (node.prev, node.sibling)
+        };
+        if let Some(prev) = prev {
+            if self.node(prev).child == Some(i) {
+                self.node_mut(prev).child = sibling;
+            } else {
+                self.node_mut(prev).sibling = sibling;
+            }
+        }
+        if let Some(sibling) = sibling {
+            self.node_mut(sibling).prev = prev;
+        }
+        self.detach(i);
+    }
+
+    pub fn is_valid_heap(&self) -> bool {
+        let mut count = 0;
+        let mut stack: Vec<usize> = self.root.into_iter().collect();
+        while let Some(i) = stack.pop() {
+            count += 1;
+            let node = self.node(i);
+            let mut child = node.child;
+            while let Some(c) = child {
+                if self.node(c).notification.no_messages > node.notification.no_messages {
+                    return false;
+                }
+                stack.push(c);
+                child = self.node(c).sibling;
+            }
+        }
+        count == self.length
+    }
+}
+
+impl PriorityQueue for PairingHeap {
+    fn push(&mut self, notification: MessageNotification) -> Handle {
+        let handle = self.allocate(Node {
+            notification,
+            child: None,
+            sibling: None,
+            prev: None,
+        });
+        self.root = self.meld(self.root, Some(handle));
+        self.length += 1;
+        handle
+    }
+
+    fn pop(&mut self) -> Option<MessageNotification> {
+        self.root.map(|root| {
+            let node = self.nodes[root].take().expect("Node was already removed");
+            self.free.push(root);
+            if let Some(child) = node.child {
+                self.node_mut(child).prev = None;
+            }
+            self.root = self.merge_pairs(node.child);
+            self.length -= 1;
+            node.notification
+        })
+    }
+
+    fn peek(&self) -> Option<&MessageNotification> {
+        self.root.map(|root| &self.node(root).notification)
+    }
+
+    fn decrease_key(&mut self, handle: Handle, no_messages: u64) -> bool {
+        match self.nodes.get(handle) {
+            Some(Some(node)) if node.notification.no_messages <= no_messages => {
+                self.node_mut(handle).notification.no_messages = no_messages;
+                if self.root != Some(handle) {
+                    self.cut(handle);
+                    self.root = self.meld(self.root, Some(handle));
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn merge(&mut self, other: PairingHeap) {
+        let offset = self.nodes.len();
+        let shift = |i: Option<usize>| i.map(|i| i + offset);
+        self.nodes.extend(other.nodes.into_iter().map(|n| {
+            n.map(|n| Node {
+                notification: n.notification,
+                child: shift(n.child),
+                sibling: shift(n.sibling),
+                prev: shift(n.prev),
+            })
+        }));
+        self.free.extend(other.free.into_iter().map(|i| i + offset));
+        self.root = self.meld(self.root, shift(other.root));
+        self.length += other.length;
+    }
+
+    fn len(&self) -> usize {
+        self.length
+    }
+}
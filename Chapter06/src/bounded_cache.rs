@@ -0,0 +1,488 @@
+use crate::map::HashMap;
+use crate::LocationInformation;
+use std::cell::Cell;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash};
+use std::mem;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+pub type BoundedLocationCache<C = SystemClock> = BoundedCache<String, LocationInformation, C>;
+
+// the recency lists; plain LRU only uses PROBATION
+const WINDOW: usize = 0;
+const PROBATION: usize = 1;
+const PROTECTED: usize = 2;
+
+const SKETCH_SEEDS: [u64; 4] = [
+    0x9E37_79B9_7F4A_7C15,
+    0xC2B2_AE3D_27D4_EB4F,
+    0x1656_67B1_9E37_79F9,
+    0x85EB_CA77_C2B2_AE63,
+];
+const MAX_FREQUENCY: u8 = 15;
+
+pub trait Clock {
+    /// The time passed since some fixed point.
+    fn now(&self) -> Duration;
+}
+
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl Default for SystemClock {
+    fn default() -> SystemClock {
+        SystemClock {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// A clock that only moves when told to. Clones share the same time.
+#[derive(Clone, Default)]
+pub struct ManualClock {
+    now: Rc<Cell<Duration>>,
+}
+
+impl ManualClock {
+    pub fn advance(&self, by: Duration) {
+        self.now.set(self.now.get() + by);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        self.now.get()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub expirations: u64,
+}
+
+impl CacheStats {
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+}
+
+struct Node<K, V> {
+    key: K,
+    value: V,
+    expires: Option<Duration>,
+    list: usize,
+    prev: Option<usize>,
+    next: Option<usize>,
+}
+
+/// Most recently used first.
+#[derive(Clone, Copy, Default)]
+struct List {
+    head: Option<usize>,
+    tail: Option<usize>,
+    len: usize,
+}
+
+/// A count-min sketch of how often keys were seen. All counters are halved
+/// every `sample_size` increments, so old popularity fades.
+struct FrequencySketch {
+    counters: Vec<u8>,
+    width: usize,
+    samples: usize,
+    sample_size: usize,
+}
+
+impl FrequencySketch {
+    fn new(capacity: usize) -> FrequencySketch {
+        let width = capacity.next_power_of_two();
+        FrequencySketch {
+            counters: vec![0; width * SKETCH_SEEDS.len()],
+            width,
+            samples: 0,
+            sample_size: width * 10,
+        }
+    }
+
+    // one counter per row
+    fn slots(&self, hash: u64) -> [usize; 4] {
+        let mut slots = [0; 4];
+        for (row, seed) in SKETCH_SEEDS.iter().enumerate() {
+            let column = (hash.wrapping_mul(*seed) >> 32) as usize & (self.width - 1);
+            slots[row] = row * self.width + column;
+        }
+        slots
+    }
+
+    fn increment(&mut self, hash: u64) {
+        for slot in self.slots(hash).iter() {
+            let counter = &mut self.counters[*slot];
+            *counter = (*counter + 1).min(MAX_FREQUENCY);
+        }
+        self.samples += 1;
+        if self.samples >= self.sample_size {
+            for counter in self.counters.iter_mut() {
+                *counter /= 2;
+            }
+            self.samples /= 2;
+        }
+    }
+
+    fn frequency(&self, hash: u64) -> u8 {
+        self.slots(hash)
+            .iter()
+            .map(|slot| self.counters[*slot])
+            .min()
+            .unwrap_or(0)
+    }
+}
+
+/// A `map::HashMap` that holds at most `capacity` entries and drops the least
+/// recently used one to make room. The recency lists are linked through the
+/// entries' slots, so moving an entry to the front is O(1).
+///
+/// Expired entries are dropped when they are found, or by `purge_expired`.
+pub struct BoundedCache<K, V, C = SystemClock, S = RandomState> {
+    index: HashMap<K, usize, S>,
+    nodes: Vec<Option<Node<K, V>>>,
+    free: Vec<usize>,
+    lists: [List; 3],
+    capacity: usize,
+    window_capacity: usize,
+    protected_capacity: usize,
+    sketch: Option<FrequencySketch>,
+    ttl: Option<Duration>,
+    clock: C,
+    stats: CacheStats,
+}
+
+impl<K, V> BoundedCache<K, V, SystemClock, RandomState>
+where
+    K: Hash + Eq + Clone,
+{
+    pub fn new(capacity: usize) -> BoundedCache<K, V> {
+        BoundedCache::with_clock(capacity, SystemClock::default())
+    }
+}
+
+impl<K, V, C> BoundedCache<K, V, C, RandomState>
+where
+    K: Hash + Eq + Clone,
+    C: Clock,
+{
+    pub fn with_clock(capacity: usize, clock: C) -> BoundedCache<K, V, C> {
+        BoundedCache::with_clock_and_hasher(capacity, clock, RandomState::new())
+    }
+}
+
+impl<K, V, C, S> BoundedCache<K, V, C, S>
+where
+    K: Hash + Eq + Clone,
+    C: Clock,
+    S: BuildHasher,
+{
+    pub fn with_clock_and_hasher(
+        capacity: usize,
+        clock: C,
+        hash_builder: S,
+    ) -> BoundedCache<K, V, C, S> {
+        assert!(capacity > 0, "the cache needs room for an entry");
+        BoundedCache {
+            index: HashMap::with_capacity_and_hasher(capacity + 1, hash_builder),
+            nodes: Vec::with_capacity(capacity + 1),
+            free: vec![],
+            lists: [List::default(); 3],
+            capacity,
+            window_capacity: 0,
+            protected_capacity: 0,
+            sketch: None,
+            ttl: None,
+            clock,
+            stats: CacheStats::default(),
+        }
+    }
+
+    /// Sets the time to live of entries added with `insert`.
+    pub fn with_ttl(mut self, ttl: Duration) -> BoundedCache<K, V, C, S> {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Switches to W-TinyLFU: new entries go into a small LRU window, and
+    /// leave it only if they were asked for more often than the entry they
+    /// would replace. Entries hit a second time are protected from that.
+    pub fn with_tiny_lfu(mut self) -> BoundedCache<K, V, C, S> {
+        self.window_capacity = (self.capacity / 100).max(1);
+        self.protected_capacity = (self.capacity - self.window_capacity) * 4 / 5;
+        self.sketch = Some(FrequencySketch::new(self.capacity));
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    fn node(&self, i: usize) -> &Node<K, V> {
+        self.nodes[i].as_ref().expect("Indexed slots hold a node")
+    }
+
+    fn node_mut(&mut self, i: usize) -> &mut Node<K, V> {
+        self.nodes[i].as_mut().expect("Indexed slots hold a node")
+    }
+
+    fn unlink(&mut self, i: usize) {
+        let (list, prev, next) = {
+            let node = self.node(i);
+            (node.list, node.prev, node.next)
+        };
+        match prev {
+            Some(p) => self.node_mut(p).next = next,
+            None => self.lists[list].head = next,
+        }
+        match next {
+            Some(n) => self.node_mut(n).prev = prev,
+            None => self.lists[list].tail = prev,
+        }
+        self.lists[list].len -= 1;
+    }
+
+    fn push_front(&mut self, list: usize, i: usize) {
+        let head = self.lists[list].head;
+        {
+            let node = self.node_mut(i);
+            node.list = list;
+            node.prev = None;
+            node.next = head;
+        }
+        match head {
+            Some(h) => self.node_mut(h).prev = Some(i),
+            None => self.lists[list].tail = Some(i),
+        }
+        self.lists[list].head = Some(i);
+        self.lists[list].len += 1;
+    }
+
+    fn move_to_front(&mut self, list: usize, i: usize) {
+        self.unlink(i);
+        self.push_front(list, i);
+    }
+
+    fn is_expired(&self, i: usize) -> bool {
+        matches!(self.node(i).expires, Some(t) if t <= self.clock.now())
+    }
+
+    fn detach(&mut self, i: usize) -> Node<K, V> {
+        self.unlink(i);
+        let node = self.nodes[i].take().expect("Indexed slots hold a node");
+        self.free.push(i);
+        self.index.remove(node.key.clone());
+        node
+    }
+
+    // the slot of a live entry, dropping it if it has expired
+    fn lookup(&mut self, key: &K) -> Option<usize> {
+        let i = *self.index.get(key)?;
+        if self.is_expired(i) {
+            self.detach(i);
+            self.stats.expirations += 1;
+            None
+        } else {
+            Some(i)
+        }
+    }
+
+    fn record(&mut self, key: &K) {
+        let hash = self.index.hasher().hash_one(key);
+        if let Some(sketch) = self.sketch.as_mut() {
+            sketch.increment(hash);
+        }
+    }
+
+    fn frequency(&self, i: usize) -> u8 {
+        let hash = self.index.hasher().hash_one(&self.node(i).key);
+        self.sketch.as_ref().map_or(0, |s| s.frequency(hash))
+    }
+
+    fn touch(&mut self, i: usize) {
+        let list = self.node(i).list;
+        if list == PROBATION && self.sketch.is_some() {
+            self.move_to_front(PROTECTED, i);
+            if self.lists[PROTECTED].len > self.protected_capacity {
+                let demoted = self.lists[PROTECTED].tail.expect("The list is not empty");
+                self.move_to_front(PROBATION, demoted);
+            }
+        } else {
+            self.move_to_front(list, i);
+        }
+    }
+
+    fn evict(&mut self, i: usize) {
+        self.detach(i);
+        self.stats.evictions += 1;
+    }
+
+    // lets the window's oldest entry into the main lists if there is room,
+    // or if it is more popular than the next entry to go from there
+    fn admit(&mut self, candidate: usize) {
+        if self.len() <= self.capacity {
+            self.move_to_front(PROBATION, candidate);
+            return;
+        }
+        let victim = self.lists[PROBATION].tail.or(self.lists[PROTECTED].tail);
+        match victim {
+            Some(v) if self.frequency(candidate) > self.frequency(v) => {
+                self.evict(v);
+                self.move_to_front(PROBATION, candidate);
+            }
+            _ => self.evict(candidate),
+        }
+    }
+
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        self.get_mut(key).map(|v| &*v)
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.record(key);
+        match self.lookup(key) {
+            Some(i) => {
+                self.stats.hits += 1;
+                self.touch(i);
+                Some(&mut self.node_mut(i).value)
+            }
+            None => {
+                self.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Like `get`, but leaves recency and statistics alone.
+    pub fn peek(&self, key: &K) -> Option<&V> {
+        let i = *self.index.get(key)?;
+        if self.is_expired(i) {
+            None
+        } else {
+            Some(&self.node(i).value)
+        }
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.peek(key).is_some()
+    }
+
+    /// Returns the value that was replaced, if any.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let ttl = self.ttl;
+        self.insert_entry(key, value, ttl)
+    }
+
+    /// Inserts an entry that expires after `ttl`, whatever the cache's default.
+    pub fn insert_with_ttl(&mut self, key: K, value: V, ttl: Duration) -> Option<V> {
+        self.insert_entry(key, value, Some(ttl))
+    }
+
+    fn insert_entry(&mut self, key: K, value: V, ttl: Option<Duration>) -> Option<V> {
+        self.record(&key);
+        // a time to live too long to represent never ends
+        let expires = ttl.and_then(|t| self.clock.now().checked_add(t));
+        if let Some(i) = self.lookup(&key) {
+            let node = self.node_mut(i);
+            node.expires = expires;
+            let old = mem::replace(&mut node.value, value);
+            self.touch(i);
+            return Some(old);
+        }
+
+        let node = Node {
+            key: key.clone(),
+            value,
+            expires,
+            list: WINDOW,
+            prev: None,
+            next: None,
+        };
+        let i = match self.free.pop() {
+            Some(i) => {
+                self.nodes[i] = Some(node);
+                i
+            }
+            None => {
+                self.nodes.push(Some(node));
+                self.nodes.len() - 1
+            }
+        };
+        self.index.insert(key, i);
+
+        if self.sketch.is_some() {
+            self.push_front(WINDOW, i);
+            if self.lists[WINDOW].len > self.window_capacity {
+                let candidate = self.lists[WINDOW].tail.expect("The list is not empty");
+                self.admit(candidate);
+            }
+        } else {
+            self.push_front(PROBATION, i);
+            if self.len() > self.capacity {
+                let victim = self.lists[PROBATION].tail.expect("The list is not empty");
+                self.evict(victim);
+            }
+        }
+        None
+    }
+
+    pub fn remove(&mut self, key: K) -> Option<V> {
+        let i = self.lookup(&key)?;
+        Some(self.detach(i).value)
+    }
+
+    /// Drops all expired entries and returns how many there were.
+    pub fn purge_expired(&mut self) -> usize {
+        let expired: Vec<usize> = (0..self.nodes.len())
+            .filter(|i| self.nodes[*i].is_some() && self.is_expired(*i))
+            .collect();
+        for i in expired.iter() {
+            self.detach(*i);
+        }
+        self.stats.expirations += expired.len() as u64;
+        expired.len()
+    }
+
+    /// The keys from most to least recently used, within each list.
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        [WINDOW, PROTECTED, PROBATION].iter().flat_map(move |list| {
+            let mut next = self.lists[*list].head;
+            std::iter::from_fn(move || {
+                let node = self.node(next?);
+                next = node.next;
+                Some(&node.key)
+            })
+        })
+    }
+}
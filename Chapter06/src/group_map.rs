@@ -0,0 +1,194 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash};
+use std::mem;
+
+const GROUP_WIDTH: usize = 8;
+// a full slot's control byte holds the top 7 bits of its spread hash
+const EMPTY: u8 = 0b1111_1111;
+const DELETED: u8 = 0b1000_0000;
+
+// spreads weak hashes over both the group index and the control byte
+const SPREAD: u64 = 0x9E37_79B9_7F4A_7C15;
+
+const LOW_BITS: u64 = 0x0101_0101_0101_0101;
+const HIGH_BITS: u64 = 0x8080_8080_8080_8080;
+
+/// Set bits mark the matching bytes of a group, lowest slot first.
+struct BitMask(u64);
+
+impl Iterator for BitMask {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.0 == 0 {
+            None
+        } else {
+            let i = self.0.trailing_zeros() as usize / 8;
+            self.0 &= self.0 - 1;
+            Some(i)
+        }
+    }
+}
+
+fn control_byte(hash: u64) -> u8 {
+    (hash >> 57) as u8
+}
+
+// may report false positives, which the key comparison filters out
+fn match_byte(group: u64, byte: u8) -> BitMask {
+    let x = group ^ (LOW_BITS * byte as u64);
+    BitMask(x.wrapping_sub(LOW_BITS) & !x & HIGH_BITS)
+}
+
+fn match_empty(group: u64) -> BitMask {
+    BitMask(group & (group << 1) & HIGH_BITS)
+}
+
+fn match_empty_or_deleted(group: u64) -> BitMask {
+    BitMask(group & HIGH_BITS)
+}
+
+/// Open addressing that probes groups of eight slots at a time, comparing
+/// their control bytes within a single `u64`.
+pub struct GroupProbingMap<K, V, S = RandomState> {
+    hash_builder: S,
+    control: Vec<u8>,
+    slots: Vec<Option<(K, V)>>,
+    deleted: usize,
+    pub length: usize,
+}
+
+impl<K, V> GroupProbingMap<K, V, RandomState>
+where
+    K: Hash + Eq,
+{
+    /// Creates a map with `length` slots, rounded up to a power of two of at
+    /// least one group.
+    pub fn new(length: usize) -> GroupProbingMap<K, V> {
+        GroupProbingMap::with_hasher(length, RandomState::new())
+    }
+}
+
+impl<K, V, S> GroupProbingMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Like `new`, but hashes keys with `hash_builder`.
+    pub fn with_hasher(length: usize, hash_builder: S) -> GroupProbingMap<K, V, S> {
+        let size = length.next_power_of_two().max(GROUP_WIDTH);
+        GroupProbingMap {
+            hash_builder,
+            control: vec![EMPTY; size],
+            slots: (0..size).map(|_| None).collect(),
+            deleted: 0,
+            length: 0,
+        }
+    }
+
+    fn group(&self, g: usize) -> u64 {
+        let mut bytes = [0; GROUP_WIDTH];
+        bytes.copy_from_slice(&self.control[g * GROUP_WIDTH..(g + 1) * GROUP_WIDTH]);
+        u64::from_le_bytes(bytes)
+    }
+
+    // triangular steps over a power-of-two number of groups visit each once
+    fn probe(&self, hash: u64) -> impl Iterator<Item = usize> {
+        let groups = self.control.len() / GROUP_WIDTH;
+        let mut g = (hash >> 32) as usize & (groups - 1);
+        (0..groups).map(move |step| {
+            g = (g + step) & (groups - 1);
+            g
+        })
+    }
+
+    fn find(&self, key: &K) -> Option<usize> {
+        let hash = self.hash(key);
+        let h2 = control_byte(hash);
+        for g in self.probe(hash) {
+            let group = self.group(g);
+            for i in match_byte(group, h2) {
+                let idx = g * GROUP_WIDTH + i;
+                if let Some((ref k, _)) = self.slots[idx] {
+                    if k == key {
+                        return Some(idx);
+                    }
+                }
+            }
+            if match_empty(group).next().is_some() {
+                return None;
+            }
+        }
+        None
+    }
+
+    fn hash(&self, key: &K) -> u64 {
+        self.hash_builder.hash_one(key).wrapping_mul(SPREAD)
+    }
+
+    fn place(&mut self, hash: u64, key: K, value: V) {
+        let target = self
+            .probe(hash)
+            .filter_map(|g| {
+                match_empty_or_deleted(self.group(g))
+                    .next()
+                    .map(|i| g * GROUP_WIDTH + i)
+            })
+            .next()
+            .expect("The table always has a free slot");
+        if self.control[target] == DELETED {
+            self.deleted -= 1;
+        }
+        self.control[target] = control_byte(hash);
+        self.slots[target] = Some((key, value));
+    }
+
+    // grows, or only clears out deleted slots if most of them are
+    fn rehash(&mut self) {
+        let size = if (self.length + 1) * 2 > self.control.len() {
+            self.control.len() * 2
+        } else {
+            self.control.len()
+        };
+        self.control = vec![EMPTY; size];
+        self.deleted = 0;
+        let old = mem::replace(&mut self.slots, (0..size).map(|_| None).collect());
+        for (key, value) in old.into_iter().flatten() {
+            let hash = self.hash(&key);
+            self.place(hash, key, value);
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.find(key)
+            .and_then(|idx| self.slots[idx].as_ref())
+            .map(|e| &e.1)
+    }
+
+    pub fn remove(&mut self, key: K) -> Option<V> {
+        let idx = self.find(&key)?;
+        self.control[idx] = DELETED;
+        self.deleted += 1;
+        self.length -= 1;
+        self.slots[idx].take().map(|e| e.1)
+    }
+
+    /// Returns the value that was replaced, if any.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.find(&key) {
+            Some(idx) => self.slots[idx]
+                .as_mut()
+                .map(|e| mem::replace(&mut e.1, value)),
+            None => {
+                // deleted slots don't end a probe, so they count towards the load
+                if (self.length + self.deleted + 1) * 8 > self.control.len() * 7 {
+                    self.rehash();
+                }
+                let hash = self.hash(&key);
+                self.place(hash, key, value);
+                self.length += 1;
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use crate::hashers::{BuildFnv1aHasher, BuildMurmur3Hasher, BuildSipHasher24, BuildXxHash64Hasher};
+use crate::{BuildAdler32Hasher, BuildHashcodeHasher};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::{BuildHasher, BuildHasherDefault, Hasher};
+
+/// Quality measures of one hash function over one set of keys.
+#[derive(Clone, Debug)]
+pub struct HashReport {
+    pub name: String,
+    pub bits: u32,
+    pub keys: usize,
+    /// keys whose hash was already taken by another key
+    pub collisions: usize,
+    pub buckets: usize,
+    pub chi_squared: f64,
+    /// average and worst deviation of an output bit's flip rate from 1/2,
+    /// scaled to 0 (ideal) ..= 1 (never or always flips)
+    pub avalanche_bias: f64,
+    pub avalanche_worst: f64,
+}
+
+impl HashReport {
+    /// Around 1.0 if keys spread evenly over the buckets.
+    pub fn chi_squared_per_bucket(&self) -> f64 {
+        self.chi_squared / (self.buckets - 1) as f64
+    }
+}
+
+impl fmt::Display for HashReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:<16} {:>2} bits  {:>6} keys  {:>6} collisions  ",
+            self.name, self.bits, self.keys, self.collisions
+        )?;
+        write!(
+            f,
+            "chi²/df {:>9.2}  avalanche bias {:.3} (worst {:.3})",
+            self.chi_squared_per_bucket(),
+            self.avalanche_bias,
+            self.avalanche_worst
+        )
+    }
+}
+
+fn hash<S: BuildHasher>(build: &S, bytes: &[u8], bits: u32) -> u64 {
+    let mut hasher = build.build_hasher();
+    hasher.write(bytes);
+    let h = hasher.finish();
+    if bits >= 64 {
+        h
+    } else {
+        h & ((1 << bits) - 1)
+    }
+}
+
+pub fn collisions<S: BuildHasher>(build: &S, bits: u32, keys: &[Vec<u8>]) -> usize {
+    let mut seen = HashSet::new();
+    keys.iter()
+        .filter(|k| !seen.insert(hash(build, k, bits)))
+        .count()
+}
+
+/// Pearson's chi-squared over `buckets` (a power of two), using the low bits
+/// of the hash like `map::HashMap` does.
+pub fn chi_squared<S: BuildHasher>(build: &S, buckets: usize, keys: &[Vec<u8>]) -> f64 {
+    let mut observed = vec![0usize; buckets];
+    for k in keys {
+        observed[hash(build, k, 64) as usize & (buckets - 1)] += 1;
+    }
+    let expected = keys.len() as f64 / buckets as f64;
+    observed
+        .into_iter()
+        .map(|o| (o as f64 - expected).powi(2) / expected)
+        .sum()
+}
+
+/// Flips every input bit of every key and returns the average and worst
+/// bias of the output bits.
+pub fn avalanche<S: BuildHasher>(build: &S, bits: u32, keys: &[Vec<u8>]) -> (f64, f64) {
+    let mut flips = vec![0usize; bits as usize];
+    let mut trials = 0;
+    for key in keys {
+        let original = hash(build, key, bits);
+        let mut changed = key.clone();
+        for bit in 0..key.len() * 8 {
+            changed[bit / 8] ^= 1 << (bit % 8);
+            let diff = original ^ hash(build, &changed, bits);
+            changed[bit / 8] ^= 1 << (bit % 8);
+
+            for (j, f) in flips.iter_mut().enumerate() {
+                *f += (diff >> j) as usize & 1;
+            }
+            trials += 1;
+        }
+    }
+    let bias: Vec<f64> = flips
+        .into_iter()
+        .map(|f| (2.0 * f as f64 / trials as f64 - 1.0).abs())
+        .collect();
+    let worst = bias.iter().cloned().fold(0.0, f64::max);
+    (bias.iter().sum::<f64>() / bias.len() as f64, worst)
+}
+
+/// Runs all measures; duplicate keys are counted once.
+pub fn analyze<S: BuildHasher>(name: &str, build: &S, bits: u32, keys: &[Vec<u8>]) -> HashReport {
+    let mut unique = HashSet::new();
+    let keys: Vec<Vec<u8>> = keys.iter().filter(|k| unique.insert(*k)).cloned().collect();
+    // about eight keys per bucket keeps the chi-squared approximation valid
+    let buckets = (keys.len() / 8).next_power_of_two().max(2);
+    let (avalanche_bias, avalanche_worst) = avalanche(build, bits, &keys);
+    HashReport {
+        name: name.to_owned(),
+        bits,
+        keys: keys.len(),
+        collisions: collisions(build, bits, &keys),
+        buckets,
+        chi_squared: chi_squared(build, buckets, &keys),
+        avalanche_bias,
+        avalanche_worst,
+    }
+}
+
+/// Reports on every hash function in this crate, and std's `DefaultHasher`.
+pub fn report(keys: &[Vec<u8>]) -> Vec<HashReport> {
+    vec![
+        analyze("adler32", &BuildAdler32Hasher::default(), 32, keys),
+        analyze("hashcode", &BuildHashcodeHasher::default(), 32, keys),
+        analyze(
+            "DefaultHasher",
+            &BuildHasherDefault::<DefaultHasher>::default(),
+            64,
+            keys,
+        ),
+        analyze("fnv1a", &BuildFnv1aHasher::default(), 64, keys),
+        analyze("murmur3", &BuildMurmur3Hasher::default(), 64, keys),
+        analyze("xxhash64", &BuildXxHash64Hasher::default(), 64, keys),
+        analyze("siphash-2-4", &BuildSipHasher24::new(1, 2), 64, keys),
+    ]
+}
+
+/// Made-up place names in the style of the `LocationCache` keys.
+pub fn location_names(n: usize) -> Vec<Vec<u8>> {
+    let prefixes = [
+        "Black", "Molten", "Ragefire", "Shadow", "Iron", "Silver", "Stone", "Storm", "Frost",
+        "Sun", "Moon", "Deep", "High", "Red", "Golden", "Thunder",
+    ];
+    let cores = [
+        "rock", "fang", "forge", "wind", "moor", "vale", "crest", "hold", "gate", "spire", "keep",
+        "fall",
+    ];
+    let suffixes = [
+        "", " Spire", " Core", " Chasm", " Keep", " Harbor", " Pass", " Depths",
+    ];
+
+    let combinations = prefixes.len() * cores.len() * suffixes.len();
+    (0..n)
+        .map(|i| {
+            let c = i % combinations;
+            let mut name = format!(
+                "{}{}{}",
+                prefixes[c % prefixes.len()],
+                cores[c / prefixes.len() % cores.len()],
+                suffixes[c / (prefixes.len() * cores.len())]
+            );
+            if i >= combinations {
+                name.push_str(&format!(" {}", i / combinations + 1));
+            }
+            name.into_bytes()
+        })
+        .collect()
+}
+
+/// Consecutive dotted IPv4 addresses from `10.0.0.1` on.
+pub fn ip_addresses(n: usize) -> Vec<Vec<u8>> {
+    (1..=n as u32)
+        .map(|i| {
+            let [_, b, c, d] = i.to_be_bytes();
+            format!("10.{}.{}.{}", b, c, d).into_bytes()
+        })
+        .collect()
+}
@@ -0,0 +1,451 @@
+use std::hash::{BuildHasher, BuildHasherDefault, Hasher};
+
+const FNV32_OFFSET: u32 = 0x811c_9dc5;
+const FNV32_PRIME: u32 = 0x0100_0193;
+const FNV64_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV64_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+const XXH_PRIME_1: u64 = 0x9E37_79B1_85EB_CA87;
+const XXH_PRIME_2: u64 = 0xC2B2_AE3D_27D4_EB4F;
+const XXH_PRIME_3: u64 = 0x1656_67B1_9E37_79F9;
+const XXH_PRIME_4: u64 = 0x85EB_CA77_C2B2_AE63;
+const XXH_PRIME_5: u64 = 0x27D4_EB2F_1656_67C5;
+
+pub type BuildFnv1aHasher = BuildHasherDefault<Fnv1aHasher>;
+pub type BuildMurmur3Hasher = BuildHasherDefault<Murmur3Hasher>;
+pub type BuildXxHash64Hasher = BuildHasherDefault<XxHash64Hasher>;
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    let mut buf = [0; 4];
+    buf.copy_from_slice(&bytes[..4]);
+    u32::from_le_bytes(buf)
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    let mut buf = [0; 8];
+    buf.copy_from_slice(&bytes[..8]);
+    u64::from_le_bytes(buf)
+}
+
+// the tail bytes of a block as a little endian integer
+fn read_tail(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .enumerate()
+        .fold(0, |acc, (i, b)| acc | (*b as u64) << (i * 8))
+}
+
+pub fn fnv1a_32(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(FNV32_OFFSET, |h, b| {
+        (h ^ *b as u32).wrapping_mul(FNV32_PRIME)
+    })
+}
+
+pub fn fnv1a_64(bytes: &[u8]) -> u64 {
+    let mut hasher = Fnv1aHasher::default();
+    hasher.write(bytes);
+    hasher.finish()
+}
+
+fn fmix32(mut h: u32) -> u32 {
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x85eb_ca6b);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xc2b2_ae35);
+    h ^ (h >> 16)
+}
+
+fn fmix64(mut k: u64) -> u64 {
+    k ^= k >> 33;
+    k = k.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    k ^= k >> 33;
+    k = k.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    k ^ (k >> 33)
+}
+
+/// MurmurHash3's x86_32 variant.
+pub fn murmur3_32(bytes: &[u8], seed: u32) -> u32 {
+    const C1: u32 = 0xcc9e_2d51;
+    const C2: u32 = 0x1b87_3593;
+    let mix = |k: u32| k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+
+    let mut h = seed;
+    let blocks = bytes.chunks_exact(4);
+    let tail = blocks.remainder();
+    for block in blocks {
+        h ^= mix(read_u32(block));
+        h = h.rotate_left(13).wrapping_mul(5).wrapping_add(0xe654_6b64);
+    }
+    if !tail.is_empty() {
+        h ^= mix(read_tail(tail) as u32);
+    }
+    fmix32(h ^ bytes.len() as u32)
+}
+
+/// MurmurHash3's x64_128 variant, as the two halves `(h1, h2)`.
+pub fn murmur3_128(bytes: &[u8], seed: u32) -> (u64, u64) {
+    let mut hasher = Murmur3Hasher::new_with_seed(seed);
+    hasher.write(bytes);
+    hasher.finish_128()
+}
+
+fn xxh_round(acc: u64, input: u64) -> u64 {
+    acc.wrapping_add(input.wrapping_mul(XXH_PRIME_2))
+        .rotate_left(31)
+        .wrapping_mul(XXH_PRIME_1)
+}
+
+fn xxh_merge(acc: u64, val: u64) -> u64 {
+    (acc ^ xxh_round(0, val))
+        .wrapping_mul(XXH_PRIME_1)
+        .wrapping_add(XXH_PRIME_4)
+}
+
+pub fn xxhash64(bytes: &[u8], seed: u64) -> u64 {
+    let mut hasher = XxHash64Hasher::new_with_seed(seed);
+    hasher.write(bytes);
+    hasher.finish()
+}
+
+/// SipHash-2-4 keyed with `k0` and `k1`.
+pub fn siphash24(k0: u64, k1: u64, bytes: &[u8]) -> u64 {
+    let mut hasher = SipHasher24::new_with_keys(k0, k1);
+    hasher.write(bytes);
+    hasher.finish()
+}
+
+/// 64 bit FNV-1a over everything written.
+pub struct Fnv1aHasher(u64);
+
+impl Default for Fnv1aHasher {
+    fn default() -> Fnv1aHasher {
+        Fnv1aHasher(FNV64_OFFSET)
+    }
+}
+
+impl Hasher for Fnv1aHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 = (self.0 ^ *b as u64).wrapping_mul(FNV64_PRIME);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+const MURMUR3_C1: u64 = 0x87c3_7b91_1142_53d5;
+const MURMUR3_C2: u64 = 0x4cf5_ad43_2745_937f;
+
+fn murmur3_mix1(k: u64) -> u64 {
+    k.wrapping_mul(MURMUR3_C1)
+        .rotate_left(31)
+        .wrapping_mul(MURMUR3_C2)
+}
+
+fn murmur3_mix2(k: u64) -> u64 {
+    k.wrapping_mul(MURMUR3_C2)
+        .rotate_left(33)
+        .wrapping_mul(MURMUR3_C1)
+}
+
+/// `murmur3_128`, one 16 byte block at a time. As a `Hasher`, it returns
+/// the first half with seed 0.
+#[derive(Clone)]
+pub struct Murmur3Hasher {
+    h1: u64,
+    h2: u64,
+    // up to 15 bytes that don't make a full block yet
+    tail: [u8; 16],
+    ntail: usize,
+    length: usize,
+}
+
+impl Default for Murmur3Hasher {
+    fn default() -> Murmur3Hasher {
+        Murmur3Hasher::new_with_seed(0)
+    }
+}
+
+impl Murmur3Hasher {
+    pub fn new_with_seed(seed: u32) -> Murmur3Hasher {
+        Murmur3Hasher {
+            h1: seed as u64,
+            h2: seed as u64,
+            tail: [0; 16],
+            ntail: 0,
+            length: 0,
+        }
+    }
+
+    fn block(&mut self, block: &[u8]) {
+        self.h1 ^= murmur3_mix1(read_u64(block));
+        self.h1 = self.h1.rotate_left(27).wrapping_add(self.h2);
+        self.h1 = self.h1.wrapping_mul(5).wrapping_add(0x52dc_e729);
+        self.h2 ^= murmur3_mix2(read_u64(&block[8..]));
+        self.h2 = self.h2.rotate_left(31).wrapping_add(self.h1);
+        self.h2 = self.h2.wrapping_mul(5).wrapping_add(0x3849_5ab5);
+    }
+
+    /// Both halves of the hash.
+    pub fn finish_128(&self) -> (u64, u64) {
+        let (mut h1, mut h2) = (self.h1, self.h2);
+        let tail = &self.tail[..self.ntail];
+        if tail.len() > 8 {
+            h2 ^= murmur3_mix2(read_tail(&tail[8..]));
+        }
+        if !tail.is_empty() {
+            h1 ^= murmur3_mix1(read_tail(&tail[..tail.len().min(8)]));
+        }
+
+        h1 ^= self.length as u64;
+        h2 ^= self.length as u64;
+        h1 = h1.wrapping_add(h2);
+        h2 = h2.wrapping_add(h1);
+        h1 = fmix64(h1);
+        h2 = fmix64(h2);
+        h1 = h1.wrapping_add(h2);
+        (h1, h2.wrapping_add(h1))
+    }
+}
+
+impl Hasher for Murmur3Hasher {
+    fn write(&mut self, mut bytes: &[u8]) {
+        self.length += bytes.len();
+        if self.ntail > 0 {
+            let n = bytes.len().min(16 - self.ntail);
+            self.tail[self.ntail..self.ntail + n].copy_from_slice(&bytes[..n]);
+            self.ntail += n;
+            bytes = &bytes[n..];
+            if self.ntail < 16 {
+                return;
+            }
+            let block = self.tail;
+            self.block(&block);
+            self.ntail = 0;
+        }
+        let blocks = bytes.chunks_exact(16);
+        let rest = blocks.remainder();
+        for block in blocks {
+            self.block(block);
+        }
+        self.tail[..rest.len()].copy_from_slice(rest);
+        self.ntail = rest.len();
+    }
+
+    fn finish(&self) -> u64 {
+        self.finish_128().0
+    }
+}
+
+/// `xxhash64`, one 32 byte stripe at a time. As a `Hasher`, it uses seed 0.
+#[derive(Clone)]
+pub struct XxHash64Hasher {
+    seed: u64,
+    v: [u64; 4],
+    // up to 31 bytes that don't make a full stripe yet
+    tail: [u8; 32],
+    ntail: usize,
+    length: usize,
+}
+
+impl Default for XxHash64Hasher {
+    fn default() -> XxHash64Hasher {
+        XxHash64Hasher::new_with_seed(0)
+    }
+}
+
+impl XxHash64Hasher {
+    pub fn new_with_seed(seed: u64) -> XxHash64Hasher {
+        XxHash64Hasher {
+            seed,
+            v: [
+                seed.wrapping_add(XXH_PRIME_1).wrapping_add(XXH_PRIME_2),
+                seed.wrapping_add(XXH_PRIME_2),
+                seed,
+                seed.wrapping_sub(XXH_PRIME_1),
+            ],
+            tail: [0; 32],
+            ntail: 0,
+            length: 0,
+        }
+    }
+
+    fn stripe(&mut self, stripe: &[u8]) {
+        for (i, acc) in self.v.iter_mut().enumerate() {
+            *acc = xxh_round(*acc, read_u64(&stripe[i * 8..]));
+        }
+    }
+}
+
+impl Hasher for XxHash64Hasher {
+    fn write(&mut self, mut bytes: &[u8]) {
+        self.length += bytes.len();
+        if self.ntail > 0 {
+            let n = bytes.len().min(32 - self.ntail);
+            self.tail[self.ntail..self.ntail + n].copy_from_slice(&bytes[..n]);
+            self.ntail += n;
+            bytes = &bytes[n..];
+            if self.ntail < 32 {
+                return;
+            }
+            let stripe = self.tail;
+            self.stripe(&stripe);
+            self.ntail = 0;
+        }
+        let stripes = bytes.chunks_exact(32);
+        let rest = stripes.remainder();
+        for stripe in stripes {
+            self.stripe(stripe);
+        }
+        self.tail[..rest.len()].copy_from_slice(rest);
+        self.ntail = rest.len();
+    }
+
+    fn finish(&self) -> u64 {
+        let v = &self.v;
+        let mut h = if self.length >= 32 {
+            let h = v[0]
+                .rotate_left(1)
+                .wrapping_add(v[1].rotate_left(7))
+                .wrapping_add(v[2].rotate_left(12))
+                .wrapping_add(v[3].rotate_left(18));
+            v.iter().fold(h, |h, acc| xxh_merge(h, *acc))
+        } else {
+            self.seed.wrapping_add(XXH_PRIME_5)
+        };
+        h = h.wrapping_add(self.length as u64);
+
+        let words = self.tail[..self.ntail].chunks_exact(8);
+        let mut rest = words.remainder();
+        for word in words {
+            h ^= xxh_round(0, read_u64(word));
+            h = h
+                .rotate_left(27)
+                .wrapping_mul(XXH_PRIME_1)
+                .wrapping_add(XXH_PRIME_4);
+        }
+        if rest.len() >= 4 {
+            h ^= (read_u32(rest) as u64).wrapping_mul(XXH_PRIME_1);
+            h = h
+                .rotate_left(23)
+                .wrapping_mul(XXH_PRIME_2)
+                .wrapping_add(XXH_PRIME_3);
+            rest = &rest[4..];
+        }
+        for b in rest {
+            h ^= (*b as u64).wrapping_mul(XXH_PRIME_5);
+            h = h.rotate_left(11).wrapping_mul(XXH_PRIME_1);
+        }
+
+        h ^= h >> 33;
+        h = h.wrapping_mul(XXH_PRIME_2);
+        h ^= h >> 29;
+        h = h.wrapping_mul(XXH_PRIME_3);
+        h ^ (h >> 32)
+    }
+}
+
+/// SipHash with 2 compression and 4 finalization rounds.
+#[derive(Clone)]
+pub struct SipHasher24 {
+    v: [u64; 4],
+    // up to seven bytes that don't make a full word yet
+    tail: u64,
+    ntail: usize,
+    length: usize,
+}
+
+impl SipHasher24 {
+    pub fn new_with_keys(k0: u64, k1: u64) -> SipHasher24 {
+        SipHasher24 {
+            v: [
+                k0 ^ 0x736f_6d65_7073_6575,
+                k1 ^ 0x646f_7261_6e64_6f6d,
+                k0 ^ 0x6c79_6765_6e65_7261,
+                k1 ^ 0x7465_6462_7974_6573,
+            ],
+            tail: 0,
+            ntail: 0,
+            length: 0,
+        }
+    }
+
+    fn round(&mut self) {
+        let v = &mut self.v;
+        v[0] = v[0].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(13) ^ v[0];
+        v[0] = v[0].rotate_left(32);
+        v[2] = v[2].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(16) ^ v[2];
+        v[0] = v[0].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(21) ^ v[0];
+        v[2] = v[2].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(17) ^ v[2];
+        v[2] = v[2].rotate_left(32);
+    }
+
+    fn compress(&mut self, m: u64) {
+        self.v[3] ^= m;
+        self.round();
+        self.round();
+        self.v[0] ^= m;
+    }
+}
+
+impl Hasher for SipHasher24 {
+    fn write(&mut self, mut bytes: &[u8]) {
+        self.length += bytes.len();
+        while self.ntail > 0 && !bytes.is_empty() {
+            self.tail |= (bytes[0] as u64) << (self.ntail * 8);
+            self.ntail = (self.ntail + 1) % 8;
+            bytes = &bytes[1..];
+            if self.ntail == 0 {
+                let m = self.tail;
+                self.compress(m);
+                self.tail = 0;
+            }
+        }
+        let words = bytes.chunks_exact(8);
+        let rest = words.remainder();
+        for word in words {
+            self.compress(read_u64(word));
+        }
+        if !rest.is_empty() {
+            self.tail = read_tail(rest);
+            self.ntail = rest.len();
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        let mut state = self.clone();
+        state.compress(self.tail | (self.length as u64) << 56);
+        state.v[2] ^= 0xff;
+        for _ in 0..4 {
+            state.round();
+        }
+        state.v.iter().fold(0, |h, v| h ^ v)
+    }
+}
+
+/// Creates `SipHasher24`s that all share the same keys.
+#[derive(Clone)]
+pub struct BuildSipHasher24 {
+    k0: u64,
+    k1: u64,
+}
+
+impl BuildSipHasher24 {
+    pub fn new(k0: u64, k1: u64) -> BuildSipHasher24 {
+        BuildSipHasher24 { k0, k1 }
+    }
+}
+
+impl BuildHasher for BuildSipHasher24 {
+    type Hasher = SipHasher24;
+
+    fn build_hasher(&self) -> SipHasher24 {
+        SipHasher24::new_with_keys(self.k0, self.k1)
+    }
+}
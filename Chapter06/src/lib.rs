@@ -1,7 +1,17 @@
 #![feature(test, bind_by_move_pattern_guards)]
 
+mod bounded_cache;
+mod group_map;
+mod hash_stats;
+mod hashers;
 mod map;
+mod robin_hood_map;
+mod rolling_hash;
 mod set;
+mod sharded_map;
+mod snapshot;
+
+use std::hash::{BuildHasherDefault, Hasher};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct LocationInformation {
@@ -10,39 +20,121 @@ pub struct LocationInformation {
     address: String,
     security_group_name: String,
 }
+
+impl LocationInformation {
+    pub fn new(
+        name: impl Into<String>,
+        opened: impl Into<String>,
+        address: impl Into<String>,
+        security_group_name: impl Into<String>,
+    ) -> LocationInformation {
+        LocationInformation {
+            name: name.into(),
+            opened: opened.into(),
+            address: address.into(),
+            security_group_name: security_group_name.into(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn opened(&self) -> &str {
+        &self.opened
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    pub fn security_group_name(&self) -> &str {
+        &self.security_group_name
+    }
+}
+
 const MOD_ADLER: u32 = 65521;
 
 pub fn adler32(bytes: &[u8]) -> u32 {
-    let mut a = 1_u32;
-    let mut b = 0_u32;
+    let mut hasher = Adler32Hasher::default();
+    hasher.write(bytes);
+    hasher.finish() as u32
+}
+
+pub fn hashcode(bytes: &[u8]) -> u32 {
+    let mut hasher = HashcodeHasher::default();
+    hasher.write(bytes);
+    hasher.finish() as u32
+}
+
+pub type BuildAdler32Hasher = BuildHasherDefault<Adler32Hasher>;
+pub type BuildHashcodeHasher = BuildHasherDefault<HashcodeHasher>;
+
+/// `adler32` over everything written, so it can be used with `Hash`.
+pub struct Adler32Hasher {
+    a: u32,
+    b: u32,
+}
+
+impl Default for Adler32Hasher {
+    fn default() -> Adler32Hasher {
+        Adler32Hasher { a: 1, b: 0 }
+    }
+}
+
+impl Hasher for Adler32Hasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.a = (self.a + *byte as u32) % MOD_ADLER;
+            self.b = (self.b + self.a) % MOD_ADLER;
+        }
+    }
 
-    for byte in bytes {
-        a = (a + *byte as u32) % MOD_ADLER;
-        b = (b + a) % MOD_ADLER;
+    fn finish(&self) -> u64 {
+        ((self.b << 16) | self.a) as u64
     }
+}
 
-    (b << 16) | a
+/// `hashcode` over everything written, so it can be used with `Hash`.
+#[derive(Default)]
+pub struct HashcodeHasher {
+    a: u32,
+    position: usize,
 }
 
-pub fn hashcode(bytes: &[u8]) -> u32 {
-    let mut a = 0_u32;
-    for (i, b) in bytes.iter().enumerate() {
-        a ^= *b as u32;
-        a <<= i % 4;
+impl Hasher for HashcodeHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.a ^= *b as u32;
+            self.a <<= self.position % 4;
+            self.position += 1;
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.a as u64
     }
-    a
 }
 
 #[cfg(test)]
 mod tests {
     extern crate test;
     use crate::*;
-    use rand::{thread_rng, Rng};
+    use rand::rngs::StdRng;
+    use rand::{thread_rng, Rng, SeedableRng};
     use std::cell::RefCell;
-    use std::collections::hash_map::DefaultHasher;
+    use std::collections::hash_map::{DefaultHasher, RandomState};
+    use std::collections::HashSet;
+    use std::hash::BuildHasher;
+    use std::hash::BuildHasherDefault;
     use std::hash::Hash;
     use std::hash::Hasher;
     use std::iter;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::mpsc::{channel, Receiver, Sender};
+    use std::sync::Mutex;
+    use std::thread;
+    use std::time::Duration;
     use test::Bencher;
 
     const NO_ITEMS: usize = 10_000;
@@ -77,8 +169,7 @@ mod tests {
     fn bench_hash_map_hashcode(b: &mut Bencher) {
         TEST_DATA.with(|location_info_cell| {
             let n = NO_ITEMS;
-            let mut m =
-                map::LocationCache::new(Box::new(|e: &String| hashcode(e.as_bytes()) as usize), n);
+            let mut m = map::LocationCache::with_hasher(n, BuildHashcodeHasher::default());
             let location_info = location_info_cell.borrow();
             for (key, value) in location_info.iter() {
                 m.insert(key.clone(), value.clone());
@@ -97,8 +188,7 @@ mod tests {
     fn bench_hash_map_adler32(b: &mut Bencher) {
         TEST_DATA.with(|location_info_cell| {
             let n = NO_ITEMS;
-            let mut m =
-                map::LocationCache::new(Box::new(|e: &String| adler32(e.as_bytes()) as usize), n);
+            let mut m = map::LocationCache::with_hasher(n, BuildAdler32Hasher::default());
             let location_info = location_info_cell.borrow();
             for (key, value) in location_info.iter() {
                 m.insert(key.clone(), value.clone());
@@ -117,14 +207,8 @@ mod tests {
     fn bench_hash_map_defaulthasher(b: &mut Bencher) {
         TEST_DATA.with(|location_info_cell| {
             let n = NO_ITEMS;
-            let mut m = map::LocationCache::new(
-                Box::new(|e: &String| {
-                    let mut h = DefaultHasher::new();
-                    e.hash(&mut h);
-                    h.finish() as usize
-                }),
-                n,
-            );
+            let mut m =
+                map::LocationCache::with_hasher(n, BuildHasherDefault::<DefaultHasher>::default());
             let location_info = location_info_cell.borrow();
             for (key, value) in location_info.iter() {
                 m.insert(key.clone(), value.clone());
@@ -174,6 +258,327 @@ mod tests {
         assert_eq!(adler32(&"ÄZZZZZZZZZZZ".to_owned().into_bytes()), 660079910);
     }
 
+    #[test]
+    fn hashers_stream_like_their_functions() {
+        let payload = "ÄZZZZZZZZZZZ".to_owned().into_bytes();
+        let (head, tail) = payload.split_at(5);
+
+        let mut h = Adler32Hasher::default();
+        h.write(head);
+        h.write(tail);
+        assert_eq!(h.finish(), adler32(&payload) as u64);
+
+        let mut h = HashcodeHasher::default();
+        h.write(head);
+        h.write(tail);
+        assert_eq!(h.finish(), hashcode(&payload) as u64);
+    }
+
+    #[test]
+    fn hashers_plug_into_std_hash_map() {
+        let mut m: std::collections::HashMap<String, u32, BuildAdler32Hasher> =
+            std::collections::HashMap::default();
+        m.insert("hello".to_owned(), 1);
+        assert_eq!(m.get("hello"), Some(&1));
+
+        let mut m: map::HashMap<String, u32, BuildHashcodeHasher> =
+            (0..100).map(|i| (i.to_string(), i)).collect();
+        assert_eq!(m.get(&"42".to_owned()), Some(&42));
+        assert_eq!(m.insert("42".to_owned(), 0), Some(42));
+        assert_eq!(m.length, 100);
+    }
+
+    #[test]
+    fn fnv1a_test() {
+        assert_eq!(hashers::fnv1a_32(b""), 0x811c_9dc5);
+        assert_eq!(hashers::fnv1a_32(b"a"), 0xe40c_292c);
+        assert_eq!(hashers::fnv1a_32(b"foobar"), 0xbf9c_f968);
+        assert_eq!(hashers::fnv1a_64(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(hashers::fnv1a_64(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(hashers::fnv1a_64(b"foobar"), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn murmur3_test() {
+        let fox = b"The quick brown fox jumps over the lazy dog";
+        assert_eq!(hashers::murmur3_32(b"", 0), 0);
+        assert_eq!(hashers::murmur3_32(b"", 1), 0x514e_28b7);
+        assert_eq!(hashers::murmur3_32(b"", 0xffff_ffff), 0x81f1_6f39);
+        assert_eq!(hashers::murmur3_32(&[0, 0, 0, 0], 0), 0x2362_f9de);
+        assert_eq!(hashers::murmur3_32(b"aaaa", 0x9747_b28c), 0x5a97_808a);
+        assert_eq!(hashers::murmur3_32(b"Hello, world!", 0x9747_b28c), 0x2488_4cba);
+        assert_eq!(hashers::murmur3_32(fox, 0x9747_b28c), 0x2fa8_26cd);
+
+        assert_eq!(hashers::murmur3_128(b"", 0), (0, 0));
+        assert_eq!(
+            hashers::murmur3_128(fox, 0),
+            (0xe34b_bc7b_bc07_1b6c, 0x7a43_3ca9_c49a_9347)
+        );
+    }
+
+    #[test]
+    fn xxhash64_test() {
+        assert_eq!(hashers::xxhash64(b"", 0), 0xef46_db37_51d8_e999);
+        assert_eq!(hashers::xxhash64(b"abc", 0), 0x44bc_2cf5_ad77_0999);
+        assert_eq!(
+            hashers::xxhash64(b"Nobody inspects the spammish repetition", 0),
+            0xfbce_a83c_8a37_8bf1
+        );
+    }
+
+    #[test]
+    fn siphash24_test() {
+        // the vectors from the SipHash paper: key 00..0f, message 00..len-1
+        let k0 = u64::from_le_bytes([0, 1, 2, 3, 4, 5, 6, 7]);
+        let k1 = u64::from_le_bytes([8, 9, 10, 11, 12, 13, 14, 15]);
+        let message: Vec<u8> = (0..64).collect();
+        assert_eq!(hashers::siphash24(k0, k1, &message[..0]), 0x726f_db47_dd0e_0e31);
+        assert_eq!(hashers::siphash24(k0, k1, &message[..1]), 0x74f8_39c5_93dc_67fd);
+        assert_eq!(hashers::siphash24(k0, k1, &message[..15]), 0xa129_ca61_49be_45e5);
+        assert_eq!(hashers::siphash24(k0, k1, &message[..63]), 0x958a_324c_eb06_4572);
+
+        // std's deprecated SipHasher is SipHash-2-4 too
+        #[allow(deprecated)]
+        let mut expected = std::hash::SipHasher::new_with_keys(k0, k1);
+        let mut h = hashers::SipHasher24::new_with_keys(k0, k1);
+        for chunk in message.chunks(3) {
+            expected.write(chunk);
+            h.write(chunk);
+            assert_eq!(h.finish(), expected.finish());
+        }
+    }
+
+    #[test]
+    fn new_hashers_stream_like_their_functions() {
+        let payload = b"Nobody inspects the spammish repetition";
+        let (head, tail) = payload.split_at(13);
+        let streamed = |mut h: Box<dyn Hasher>| {
+            h.write(head);
+            h.write(tail);
+            h.finish()
+        };
+        assert_eq!(
+            streamed(Box::<hashers::Fnv1aHasher>::default()),
+            hashers::fnv1a_64(payload)
+        );
+        assert_eq!(
+            streamed(Box::<hashers::Murmur3Hasher>::default()),
+            hashers::murmur3_128(payload, 0).0
+        );
+        assert_eq!(
+            streamed(Box::<hashers::XxHash64Hasher>::default()),
+            hashers::xxhash64(payload, 0)
+        );
+        assert_eq!(
+            streamed(Box::new(hashers::SipHasher24::new_with_keys(1, 2))),
+            hashers::siphash24(1, 2, payload)
+        );
+
+        // a byte at a time, so every write lands part way through a block
+        let mut murmur = hashers::Murmur3Hasher::default();
+        for b in b"The quick brown fox jumps over the lazy dog".iter() {
+            murmur.write_u8(*b);
+        }
+        assert_eq!(
+            murmur.finish_128(),
+            (0xe34b_bc7b_bc07_1b6c, 0x7a43_3ca9_c49a_9347)
+        );
+        let mut xxh = hashers::XxHash64Hasher::default();
+        for b in payload.iter() {
+            xxh.write_u8(*b);
+        }
+        assert_eq!(xxh.finish(), 0xfbce_a83c_8a37_8bf1);
+
+        let mut m = map::HashMap::with_hasher(16, hashers::BuildSipHasher24::new(1, 2));
+        m.insert("hello".to_owned(), 1);
+        assert_eq!(m.get(&"hello".to_owned()), Some(&1));
+    }
+
+    macro_rules! hash_throughput_benches {
+        ($name:ident, $hash:expr) => {
+            mod $name {
+                use super::*;
+
+                fn bench_length(b: &mut Bencher, length: usize) {
+                    let payload: Vec<u8> = (0..length).map(|i| i as u8).collect();
+                    b.bytes = length as u64;
+                    b.iter(|| $hash(test::black_box(&payload)));
+                }
+
+                #[bench]
+                fn bench_8_bytes(b: &mut Bencher) {
+                    bench_length(b, 8);
+                }
+
+                #[bench]
+                fn bench_64_bytes(b: &mut Bencher) {
+                    bench_length(b, 64);
+                }
+
+                #[bench]
+                fn bench_1_kib(b: &mut Bencher) {
+                    bench_length(b, 1024);
+                }
+
+                #[bench]
+                fn bench_64_kib(b: &mut Bencher) {
+                    bench_length(b, 64 * 1024);
+                }
+            }
+        };
+    }
+
+    hash_throughput_benches!(adler32_throughput, |p: &[u8]| adler32(p));
+    hash_throughput_benches!(fnv1a_32_throughput, |p: &[u8]| hashers::fnv1a_32(p));
+    hash_throughput_benches!(fnv1a_64_throughput, |p: &[u8]| hashers::fnv1a_64(p));
+    hash_throughput_benches!(murmur3_32_throughput, |p: &[u8]| hashers::murmur3_32(p, 0));
+    hash_throughput_benches!(murmur3_128_throughput, |p: &[u8]| hashers::murmur3_128(p, 0));
+    hash_throughput_benches!(xxhash64_throughput, |p: &[u8]| hashers::xxhash64(p, 0));
+    hash_throughput_benches!(siphash24_throughput, |p: &[u8]| hashers::siphash24(1, 2, p));
+
+    fn random_bytes(n: usize, seed: u64) -> Vec<u8> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..n).map(|_| rng.gen()).collect()
+    }
+
+    #[test]
+    fn rolling_adler32_matches_adler32() {
+        let payload = random_bytes(1000, 1);
+        let window = 64;
+        let mut checksum = rolling_hash::RollingAdler32::new(&payload[..window]);
+        assert_eq!(checksum.hash(), adler32(&payload[..window]));
+
+        for i in window..payload.len() {
+            checksum.roll(payload[i - window], payload[i]);
+            assert_eq!(checksum.hash(), adler32(&payload[i + 1 - window..=i]));
+        }
+        assert_eq!(checksum.length, window);
+
+        for b in &payload[payload.len() - window..] {
+            checksum.roll_out(*b);
+        }
+        assert_eq!(checksum, rolling_hash::RollingAdler32::default());
+        assert_eq!(checksum.hash(), adler32(&[]));
+    }
+
+    #[test]
+    fn rolling_adler32_window_larger_than_modulus() {
+        let payload = vec![0xff; 70_000];
+        let mut checksum = rolling_hash::RollingAdler32::new(&payload);
+        checksum.roll(0xff, b'a');
+        let mut expected = payload[1..].to_vec();
+        expected.push(b'a');
+        assert_eq!(checksum.hash(), adler32(&expected));
+    }
+
+    #[test]
+    fn chunker_covers_stream_within_sizes() {
+        let payload = random_bytes(200_000, 2);
+        let boundaries = rolling_hash::Chunker::new(48, 512, 2048, 8192).boundaries(&payload);
+
+        assert_eq!(boundaries.last(), Some(&payload.len()));
+        let mut start = 0;
+        for (i, end) in boundaries.iter().enumerate() {
+            let size = end - start;
+            assert!(size <= 8192);
+            if i + 1 < boundaries.len() {
+                assert!(size >= 512);
+            }
+            start = *end;
+        }
+        // random content should average close to the target size
+        let average = payload.len() / boundaries.len();
+        assert!(average > 1024 && average < 4096);
+
+        assert_eq!(rolling_hash::Chunker::new(48, 512, 2048, 8192).boundaries(&[]), vec![]);
+    }
+
+    #[test]
+    fn chunker_ignores_how_the_stream_is_split() {
+        let payload = random_bytes(50_000, 3);
+        let expected = rolling_hash::Chunker::new(32, 256, 1024, 4096).boundaries(&payload);
+
+        let mut chunker = rolling_hash::Chunker::new(32, 256, 1024, 4096);
+        let mut boundaries = vec![];
+        for part in payload.chunks(777) {
+            boundaries.extend(chunker.update(part));
+        }
+        boundaries.extend(chunker.finish());
+        assert_eq!(boundaries, expected);
+    }
+
+    #[test]
+    fn chunker_boundaries_resync_after_an_edit() {
+        let payload = random_bytes(100_000, 4);
+        let mut edited = payload.clone();
+        edited.splice(1000..1000, b"an inserted line\n".iter().cloned());
+        let shift = edited.len() - payload.len();
+
+        let before = rolling_hash::Chunker::new(48, 256, 1024, 8192).boundaries(&payload);
+        let after: HashSet<usize> = rolling_hash::Chunker::new(48, 256, 1024, 8192)
+            .boundaries(&edited)
+            .into_iter()
+            .collect();
+
+        // all boundaries past the next few ones after the edit stay in place
+        let moved: Vec<&usize> = before
+            .iter()
+            .filter(|b| **b > 1000 && !after.contains(&(**b + shift)))
+            .collect();
+        assert!(moved.len() <= 2);
+    }
+
+    #[bench]
+    fn bench_chunker(b: &mut Bencher) {
+        let payload = random_bytes(1 << 20, 5);
+        b.bytes = payload.len() as u64;
+        b.iter(|| rolling_hash::Chunker::new(48, 2048, 8192, 65536).boundaries(&payload));
+    }
+
+    #[test]
+    fn hash_stats_key_sets() {
+        let names = hash_stats::location_names(2000);
+        assert_eq!(names.len(), 2000);
+        assert_eq!(names.iter().collect::<HashSet<_>>().len(), 2000);
+        assert_eq!(hash_stats::ip_addresses(257)[256], b"10.0.1.1".to_vec());
+    }
+
+    #[test]
+    fn hash_stats_report_ranks_hashers() {
+        for keys in [
+            hash_stats::location_names(2000),
+            hash_stats::ip_addresses(2000),
+        ] {
+            let report = hash_stats::report(&keys);
+            assert_eq!(report.len(), 7);
+            let default = report.iter().find(|r| r.name == "DefaultHasher").unwrap();
+            let strong = ["DefaultHasher", "murmur3", "xxhash64", "siphash-2-4"];
+            for r in report.iter().filter(|r| strong.contains(&&*r.name)) {
+                assert_eq!(r.keys, 2000);
+                assert_eq!(r.collisions, 0);
+                assert!(r.chi_squared_per_bucket() < 1.5);
+                assert!(r.avalanche_bias < 0.05);
+            }
+
+            let weak = ["adler32", "hashcode"];
+            for r in report.iter().filter(|r| weak.contains(&&*r.name)) {
+                assert!(r.avalanche_bias > 0.5);
+                assert!(r.avalanche_worst > default.avalanche_worst);
+            }
+        }
+    }
+
+    #[test]
+    fn hash_stats_counts_collisions() {
+        let keys = vec![b"ab".to_vec(), b"ba".to_vec(), b"ab".to_vec()];
+        let report = hash_stats::analyze("adler32", &BuildAdler32Hasher::default(), 32, &keys);
+        assert_eq!(report.keys, 2);
+        assert_eq!(report.collisions, 0);
+        // hashcode xors the first two bytes before shifting them
+        let keys = vec![b"ab".to_vec(), b"ba".to_vec()];
+        assert_eq!(hash_stats::collisions(&BuildHashcodeHasher::default(), 32, &keys), 1);
+    }
+
     #[test]
     fn trie_set_insert() {
         let mut m = set::NetworkDeviceStore::new_empty();
@@ -294,77 +699,925 @@ mod tests {
         }
     }
 
+    // hashes only the length of what it's fed, so equally long keys collide
+    #[derive(Default)]
+    struct LengthHasher(u64);
+
+    impl Hasher for LengthHasher {
+        fn finish(&self) -> u64 {
+            self.0
+        }
+
+        fn write(&mut self, bytes: &[u8]) {
+            self.0 += bytes.len() as u64;
+        }
+    }
+
+    type BuildLengthHasher = BuildHasherDefault<LengthHasher>;
+
+    // "MoltenCore" and "Stratholme" end up in the same bucket
+    fn colliding_locations() -> Vec<(String, LocationInformation)> {
+        ["BlackRockSpire", "MoltenCore", "RagefireChasm", "Stratholme"]
+            .iter()
+            .map(|k| (k.to_string(), new_location_info(k)))
+            .collect()
+    }
+
     #[test]
     fn hash_map_insert() {
-        let mut m = map::LocationCache::new(Box::new(|e: &String| e.len()), 3);
-        let val = vec![
-            (
-                "BlackRockSpire".to_owned(),
-                new_location_info("BlackRockSpire"),
-            ),
-            ("MoltenCore".to_owned(), new_location_info("MoltenCore")),
-            (
-                "RagefireChasm".to_owned(),
-                new_location_info("RagefireChasm"),
-            ),
-        ];
+        let mut m = map::LocationCache::with_hasher(3, BuildLengthHasher::default());
+        let val = colliding_locations();
 
-        m.insert(val[0].0.clone(), val[0].1.clone());
-        m.insert(val[1].0.clone(), val[1].1.clone());
-        m.insert(val[2].0.clone(), val[2].1.clone());
+        for (k, v) in val.iter() {
+            assert_eq!(m.insert(k.clone(), v.clone()), None);
+        }
 
         assert_eq!(m.length, val.len());
+        for (k, v) in val.iter() {
+            assert_eq!(m.get(k), Some(v));
+        }
     }
 
     #[test]
     fn hash_map_remove() {
-        let mut m = map::LocationCache::new(Box::new(|e: &String| e.len()), 3);
-        let val = vec![
-            (
-                "BlackRockSpire".to_owned(),
-                new_location_info("BlackRockSpire"),
-            ),
-            ("MoltenCore".to_owned(), new_location_info("MoltenCore")),
-            (
-                "RagefireChasm".to_owned(),
-                new_location_info("RagefireChasm"),
-            ),
-        ];
+        let mut m = map::LocationCache::with_hasher(3, BuildLengthHasher::default());
+        let val = colliding_locations();
 
-        m.insert(val[0].0.clone(), val[0].1.clone());
-        m.insert(val[1].0.clone(), val[1].1.clone());
-        m.insert(val[2].0.clone(), val[2].1.clone());
+        for (k, v) in val.iter() {
+            m.insert(k.clone(), v.clone());
+        }
 
         assert_eq!(m.length, val.len());
 
-        assert_eq!(m.remove(val[0].0.clone()), Some(val[0].1.clone()));
         assert_eq!(m.remove(val[1].0.clone()), Some(val[1].1.clone()));
+        assert_eq!(m.remove(val[1].0.clone()), None);
+        assert_eq!(m.get(&val[3].0), Some(&val[3].1));
+        assert_eq!(m.remove(val[3].0.clone()), Some(val[3].1.clone()));
+        assert_eq!(m.remove(val[0].0.clone()), Some(val[0].1.clone()));
         assert_eq!(m.length, 1);
+        assert_eq!(m.get(&val[2].0), Some(&val[2].1));
     }
 
     #[test]
     fn hash_map_get() {
-        let mut m = map::LocationCache::new(Box::new(|e: &String| e.len()), 3);
-        let val = vec![
-            (
-                "BlackRockSpire".to_owned(),
-                new_location_info("BlackRockSpire"),
-            ),
-            ("MoltenCore".to_owned(), new_location_info("MoltenCore")),
-            (
-                "RagefireChasm".to_owned(),
-                new_location_info("RagefireChasm"),
-            ),
-        ];
+        let mut m = map::LocationCache::with_hasher(3, BuildLengthHasher::default());
+        let val = colliding_locations();
 
-        m.insert(val[0].0.clone(), val[0].1.clone());
-        m.insert(val[1].0.clone(), val[1].1.clone());
-        m.insert(val[2].0.clone(), val[2].1.clone());
+        for (k, v) in val.iter() {
+            m.insert(k.clone(), v.clone());
+        }
 
         assert_eq!(m.length, val.len());
 
-        assert_eq!(m.get(&val[0].0), Some(val[0].1.clone()));
-        assert_eq!(m.get(&val[1].0), Some(val[1].1.clone()));
+        assert_eq!(m.get(&val[1].0), Some(&val[1].1));
+        assert_eq!(m.get(&val[3].0), Some(&val[3].1));
+        // as long as the colliding keys, but missing
+        assert_eq!(m.get(&"Gnomeregan".to_owned()), None);
         assert_eq!(m.length, val.len());
     }
+
+    #[test]
+    fn hash_map_insert_returns_previous_value() {
+        let mut m = map::LocationCache::new(3);
+        let a = new_location_info("Stratholme");
+        let b = new_location_info("Scholomance");
+
+        assert_eq!(m.insert("Stratholme".to_owned(), a.clone()), None);
+        assert_eq!(m.insert("Stratholme".to_owned(), b.clone()), Some(a));
+        assert_eq!(m.get(&"Stratholme".to_owned()), Some(&b));
+        assert!(m.contains_key(&"Stratholme".to_owned()));
+        assert!(!m.contains_key(&"Scholomance".to_owned()));
+        assert_eq!(m.length, 1);
+    }
+
+    #[test]
+    fn hash_map_get_mut_and_entry() {
+        let mut m: map::HashMap<String, u32> = map::HashMap::new(1);
+        let words = "a b a c b a d a".split(' ');
+        for w in words {
+            *m.entry(w.to_owned()).or_insert(0) += 1;
+        }
+        assert_eq!(m.get(&"a".to_owned()), Some(&4));
+        assert_eq!(m.get(&"d".to_owned()), Some(&1));
+        assert_eq!(m.length, 4);
+
+        *m.get_mut(&"d".to_owned()).unwrap() = 10;
+        assert_eq!(m.get(&"d".to_owned()), Some(&10));
+        assert_eq!(m.get_mut(&"e".to_owned()), None);
+
+        let calls = RefCell::new(0);
+        let mut count = || {
+            *calls.borrow_mut() += 1;
+            7
+        };
+        assert_eq!(*m.entry("a".to_owned()).or_insert_with(&mut count), 4);
+        assert_eq!(*m.entry("e".to_owned()).or_insert_with(&mut count), 7);
+        assert_eq!(*calls.borrow(), 1);
+
+        m.entry("b".to_owned()).and_modify(|v| *v *= 100).or_insert(0);
+        assert_eq!(m.get(&"b".to_owned()), Some(&200));
+        assert_eq!(m.entry("f".to_owned()).key(), "f");
+        assert_eq!(m.length, 5);
+    }
+
+    #[test]
+    fn hash_map_iterators() {
+        let mut m: map::HashMap<u32, u32> = map::HashMap::new(1);
+        m.extend((0..100).map(|i| (i, i * 2)));
+        assert!(m.is_resizing());
+        assert_eq!(m.length, 100);
+
+        let mut keys: Vec<u32> = m.keys().cloned().collect();
+        keys.sort();
+        assert_eq!(keys, (0..100).collect::<Vec<u32>>());
+        assert_eq!(m.values().sum::<u32>(), (0..100).map(|i| i * 2).sum());
+        assert!(m.iter().all(|(k, v)| *v == k * 2));
+
+        for (k, v) in m.iter_mut() {
+            *v += k;
+        }
+        assert!(m.iter().all(|(k, v)| *v == k * 3));
+
+        let mut drained: Vec<(u32, u32)> = m.drain().collect();
+        drained.sort();
+        assert_eq!(drained, (0..100).map(|i| (i, i * 3)).collect::<Vec<_>>());
+        assert_eq!(m.length, 0);
+        assert!(!m.is_resizing());
+        assert_eq!(m.iter().count(), 0);
+        assert_eq!(m.get(&1), None);
+
+        m.insert(1, 1);
+        assert_eq!(m.get(&1), Some(&1));
+        // everything is gone, even if the drained entries aren't used
+        m.drain().next();
+        assert_eq!(m.iter().count(), 0);
+    }
+
+    #[test]
+    fn hash_map_from_iterator() {
+        let items = random_location_info(100);
+        let m: map::LocationCache = items.iter().cloned().collect();
+        let expected: std::collections::HashMap<_, _> = items.into_iter().collect();
+
+        assert_eq!(m.length, expected.len());
+        for (key, value) in expected.iter() {
+            assert_eq!(m.get(key), Some(value));
+        }
+    }
+
+    #[test]
+    fn hash_map_rounds_buckets_to_power_of_two() {
+        let m = map::LocationCache::new(3);
+        assert_eq!(m.buckets(), 4);
+        assert_eq!(m.capacity(), 3);
+
+        let m = map::LocationCache::with_capacity(100);
+        assert_eq!(m.buckets(), 256);
+        assert!(m.capacity() >= 100);
+
+        let m = map::LocationCache::new(0);
+        assert_eq!(m.buckets(), 1);
+    }
+
+    #[test]
+    fn hash_map_grows_incrementally() {
+        let mut m = map::LocationCache::with_hasher(1, BuildAdler32Hasher::default());
+        let items = random_location_info(1_000);
+        let mut inserted = vec![];
+        let mut resizes = 0;
+
+        for (key, value) in items {
+            let was_resizing = m.is_resizing();
+            m.insert(key.clone(), value.clone());
+            if m.is_resizing() && !was_resizing {
+                resizes += 1;
+            }
+            inserted.push((key, value));
+            // entries are found in either table while a resize is under way
+            for (key, value) in inserted.iter().rev().take(20) {
+                assert_eq!(m.get(key), Some(value));
+            }
+        }
+        assert!(resizes >= 8);
+        let unique: HashSet<&String> = inserted.iter().map(|e| &e.0).collect();
+        assert_eq!(m.length, unique.len());
+
+        for (key, _) in inserted.iter() {
+            m.remove(key.clone());
+        }
+        assert_eq!(m.length, 0);
+        assert!(!m.is_resizing());
+    }
+
+    #[test]
+    fn hash_map_reserve_and_shrink() {
+        let mut m = map::LocationCache::with_hasher(4, BuildAdler32Hasher::default());
+        m.reserve(1_000);
+        assert!(m.capacity() >= 1_000);
+        assert!(m.buckets().is_power_of_two());
+
+        let items = random_location_info(500);
+        let buckets = m.buckets();
+        for (key, value) in items.iter() {
+            m.insert(key.clone(), value.clone());
+        }
+        assert_eq!(m.buckets(), buckets);
+
+        for (key, _) in items.iter().skip(10) {
+            m.remove(key.clone());
+        }
+        m.shrink_to_fit();
+        assert!(m.buckets() < buckets);
+        assert!(m.capacity() >= m.length);
+        for (key, value) in items.iter().take(10) {
+            assert_eq!(m.get(key), Some(value));
+        }
+        for (key, _) in items.iter().skip(10) {
+            if !items.iter().take(10).any(|e| e.0 == *key) {
+                assert_eq!(m.get(key), None);
+            }
+        }
+    }
+
+    #[test]
+    fn hash_map_max_load_factor() {
+        let mut m = map::LocationCache::new(8);
+        m.set_max_load_factor(4.0);
+        assert_eq!(m.capacity(), 32);
+
+        for (key, value) in random_location_info(32) {
+            m.insert(key, value);
+        }
+        assert_eq!(m.buckets(), 8);
+        assert!(m.load_factor() <= 4.0);
+
+        m.set_max_load_factor(0.5);
+        assert!(m.capacity() >= m.length);
+        assert!(m.buckets() >= 16);
+    }
+
+    #[test]
+    fn bounded_cache_evicts_least_recently_used() {
+        let mut cache = bounded_cache::BoundedCache::new(3);
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        cache.insert("c", 3);
+        assert_eq!(cache.get(&"a"), Some(&1));
+        assert_eq!(cache.insert("d", 4), None);
+
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.get(&"b"), None);
+        assert_eq!(cache.keys().cloned().collect::<Vec<_>>(), vec!["d", "a", "c"]);
+        assert_eq!(cache.insert("c", 30), Some(3));
+        assert_eq!(cache.remove("a"), Some(1));
+        assert_eq!(cache.peek(&"a"), None);
+
+        let stats = cache.stats();
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.evictions, 1);
+        assert_eq!(stats.hit_rate(), 0.5);
+    }
+
+    #[test]
+    fn bounded_cache_matches_a_simple_lru() {
+        let mut rng = thread_rng();
+        let mut cache = bounded_cache::BoundedCache::new(8);
+        // most recently used last
+        let mut model: Vec<(u32, u32)> = vec![];
+
+        for i in 0..5000 {
+            let key = rng.gen_range(0, 20);
+            if rng.gen() {
+                let expected = model.iter().position(|e| e.0 == key).map(|p| model.remove(p));
+                assert_eq!(cache.insert(key, i), expected.map(|e| e.1));
+                model.push((key, i));
+                if model.len() > 8 {
+                    model.remove(0);
+                }
+            } else {
+                let expected = model.iter().position(|e| e.0 == key).map(|p| model.remove(p));
+                assert_eq!(cache.get(&key).cloned(), expected.map(|e| e.1));
+                model.extend(expected);
+            }
+        }
+        let keys: Vec<u32> = model.iter().rev().map(|e| e.0).collect();
+        assert_eq!(cache.keys().cloned().collect::<Vec<_>>(), keys);
+    }
+
+    #[test]
+    fn bounded_cache_expires_entries() {
+        let clock = bounded_cache::ManualClock::default();
+        let mut cache = bounded_cache::BoundedLocationCache::with_clock(10, clock.clone())
+            .with_ttl(Duration::from_secs(60));
+
+        let data = random_location_info(3);
+        cache.insert(data[0].0.clone(), data[0].1.clone());
+        cache.insert_with_ttl(data[1].0.clone(), data[1].1.clone(), Duration::from_secs(600));
+        cache.insert_with_ttl(data[2].0.clone(), data[2].1.clone(), Duration::from_secs(30));
+
+        clock.advance(Duration::from_secs(59));
+        assert_eq!(cache.get(&data[0].0), Some(&data[0].1));
+        assert!(!cache.contains_key(&data[2].0));
+        assert_eq!(cache.get(&data[2].0), None);
+        assert_eq!(cache.stats().expirations, 1);
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(cache.peek(&data[0].0), None);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.purge_expired(), 1);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get(&data[1].0), Some(&data[1].1));
+
+        // a refreshed entry gets a new expiry
+        cache.insert(data[0].0.clone(), data[0].1.clone());
+        clock.advance(Duration::from_secs(59));
+        assert!(cache.contains_key(&data[0].0));
+        assert_eq!(cache.stats().expirations, 2);
+
+        cache.insert_with_ttl(data[2].0.clone(), data[2].1.clone(), Duration::MAX);
+        clock.advance(Duration::from_secs(365 * 24 * 60 * 60));
+        assert_eq!(cache.get(&data[2].0), Some(&data[2].1));
+    }
+
+    #[test]
+    fn bounded_cache_tiny_lfu_resists_scans() {
+        let clock = bounded_cache::ManualClock::default();
+        let mut lru = bounded_cache::BoundedCache::with_clock(100, clock.clone());
+        let mut tiny_lfu = bounded_cache::BoundedCache::with_clock(100, clock).with_tiny_lfu();
+
+        for cache in [&mut lru, &mut tiny_lfu] {
+            for _ in 0..5 {
+                for hot in 0..50 {
+                    if cache.get(&hot).is_none() {
+                        cache.insert(hot, hot);
+                    }
+                }
+            }
+            // a scan over keys that are used only once
+            for cold in 1000..2000 {
+                cache.insert(cold, cold);
+            }
+            assert_eq!(cache.len(), 100);
+        }
+
+        assert_eq!((0..50).filter(|k| lru.contains_key(k)).count(), 0);
+        assert!((0..50).filter(|k| tiny_lfu.contains_key(k)).count() >= 45);
+        assert!(tiny_lfu.stats().evictions >= 1000 - 50);
+    }
+
+    #[bench]
+    fn bench_bounded_cache_lru_zipf(b: &mut Bencher) {
+        let keys = zipf_keys(NO_ITEMS);
+        let mut cache = bounded_cache::BoundedCache::new(NO_ITEMS / 100);
+        b.iter(|| {
+            for k in keys.iter() {
+                if cache.get(k).is_none() {
+                    cache.insert(*k, *k);
+                }
+            }
+        });
+    }
+
+    #[bench]
+    fn bench_bounded_cache_tiny_lfu_zipf(b: &mut Bencher) {
+        let keys = zipf_keys(NO_ITEMS);
+        let mut cache = bounded_cache::BoundedCache::new(NO_ITEMS / 100).with_tiny_lfu();
+        b.iter(|| {
+            for k in keys.iter() {
+                if cache.get(k).is_none() {
+                    cache.insert(*k, *k);
+                }
+            }
+        });
+    }
+
+    // roughly Zipf distributed: key k comes up about 1/k as often as key 1
+    fn zipf_keys(n: usize) -> Vec<usize> {
+        let mut rng = thread_rng();
+        (0..n)
+            .map(|_| (n as f64).powf(rng.gen::<f64>()) as usize)
+            .collect()
+    }
+
+    #[test]
+    fn location_information_accessors() {
+        let info = LocationInformation::new("Ragefire", "2018", "1 Curved Way", "Ragefire-sec");
+        assert_eq!(info.name(), "Ragefire");
+        assert_eq!(info.opened(), "2018");
+        assert_eq!(info.address(), "1 Curved Way");
+        assert_eq!(info.security_group_name(), "Ragefire-sec");
+        assert_eq!(new_location_info("Ragefire").security_group_name(), "Ragefire-sec");
+    }
+
+    fn snapshot_of(data: &[(String, LocationInformation)]) -> Vec<u8> {
+        let cache: map::LocationCache = data.iter().cloned().collect();
+        snapshot::write_snapshot(&cache, vec![]).unwrap()
+    }
+
+    #[test]
+    fn snapshot_round_trip() {
+        let data = random_location_info(500);
+        let expected: map::LocationCache = data.iter().cloned().collect();
+        let cache = snapshot::read_snapshot(&snapshot_of(&data)[..]).unwrap();
+
+        assert_eq!(cache.length, expected.length);
+        for (key, info) in expected.iter() {
+            assert_eq!(cache.get(key), Some(info));
+        }
+
+        let empty = snapshot_of(&[]);
+        assert_eq!(empty.len(), 20);
+        assert_eq!(snapshot::read_snapshot(&empty[..]).unwrap().length, 0);
+    }
+
+    #[test]
+    fn snapshot_streams_entries() {
+        let data = random_location_info(10);
+        let mut writer = snapshot::SnapshotWriter::new(vec![], data.len()).unwrap();
+        for (key, info) in data.iter() {
+            writer.write_entry(key, info).unwrap();
+        }
+        assert!(writer.write_entry("one too many", &data[0].1).is_err());
+        let bytes = writer.finish().unwrap();
+
+        let mut reader = snapshot::SnapshotReader::new(&bytes[..]).unwrap();
+        assert_eq!(reader.remaining(), 10);
+        assert_eq!(reader.minor_version(), snapshot::MINOR_VERSION);
+        let entries: Vec<_> = reader.by_ref().map(|e| e.unwrap()).collect();
+        assert_eq!(entries, data);
+        assert!(reader.next().is_none());
+
+        let writer = snapshot::SnapshotWriter::new(vec![], 2).unwrap();
+        assert!(writer.finish().is_err());
+    }
+
+    #[test]
+    fn snapshot_detects_corruption() {
+        let bytes = snapshot_of(&random_location_info(20));
+
+        for i in (16..bytes.len()).step_by(7) {
+            let mut damaged = bytes.clone();
+            damaged[i] ^= 0x20;
+            match snapshot::read_snapshot(&damaged[..]) {
+                Err(snapshot::SnapshotError::ChecksumMismatch { .. })
+                | Err(snapshot::SnapshotError::Corrupt(_))
+                | Err(snapshot::SnapshotError::Truncated) => {}
+                other => panic!("byte {} flipped unnoticed: {:?}", i, other.map(|c| c.length)),
+            }
+        }
+
+        for length in [0, 6, 12, 16, 40, bytes.len() - 1] {
+            assert!(snapshot::read_snapshot(&bytes[..length]).is_err());
+        }
+        assert!(matches!(
+            snapshot::read_snapshot(&bytes[..bytes.len() - 1]),
+            Err(snapshot::SnapshotError::Truncated)
+        ));
+        assert!(matches!(
+            snapshot::read_snapshot(&b"PK\x03\x04 not a snapshot"[..]),
+            Err(snapshot::SnapshotError::NotASnapshot)
+        ));
+
+        let mut newer = bytes.clone();
+        newer[4] = snapshot::MAJOR_VERSION + 1;
+        assert!(matches!(
+            snapshot::read_snapshot(&newer[..]),
+            Err(snapshot::SnapshotError::UnsupportedVersion(2, 0))
+        ));
+        let error = snapshot::read_snapshot(&newer[..]).err().unwrap();
+        assert_eq!(error.to_string(), "unsupported snapshot version 2.0");
+        assert!(std::error::Error::source(&error).is_none());
+    }
+
+    #[test]
+    fn snapshot_reads_later_minor_versions() {
+        // a version 1.3 snapshot with an extra header field and an extra record field
+        let mut bytes = b"LOCS".to_vec();
+        bytes.extend_from_slice(&[snapshot::MAJOR_VERSION, 3]);
+        bytes.extend_from_slice(&12u16.to_le_bytes());
+        bytes.extend_from_slice(&1u64.to_le_bytes());
+        bytes.extend_from_slice(&[0xAA; 4]);
+
+        let info = new_location_info("Stonekeep");
+        let fields = ["Stonekeep", info.name(), info.opened(), info.address()];
+        let mut record = vec![];
+        for field in fields.iter().chain(&[info.security_group_name(), "a new field"]) {
+            record.extend_from_slice(&(field.len() as u32).to_le_bytes());
+            record.extend_from_slice(field.as_bytes());
+        }
+        bytes.extend_from_slice(&(record.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&record);
+        let checksum = adler32(&bytes);
+        bytes.extend_from_slice(&checksum.to_le_bytes());
+
+        let mut reader = snapshot::SnapshotReader::new(&bytes[..]).unwrap();
+        assert_eq!(reader.minor_version(), 3);
+        assert_eq!(reader.next().unwrap().unwrap(), ("Stonekeep".to_owned(), info));
+        assert!(reader.next().is_none());
+    }
+
+    #[bench]
+    fn bench_snapshot_write(b: &mut Bencher) {
+        let cache: map::LocationCache = random_location_info(NO_ITEMS).into_iter().collect();
+        b.iter(|| snapshot::write_snapshot(&cache, Vec::with_capacity(1 << 20)).unwrap());
+    }
+
+    #[bench]
+    fn bench_snapshot_read(b: &mut Bencher) {
+        let bytes = snapshot_of(&random_location_info(NO_ITEMS));
+        b.bytes = bytes.len() as u64;
+        b.iter(|| snapshot::read_snapshot(&bytes[..]).unwrap());
+    }
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn sharded_map_is_send_and_sync() {
+        assert_send_sync::<sharded_map::ConcurrentLocationCache>();
+    }
+
+    #[test]
+    fn sharded_map_insert_get_remove() {
+        let m = sharded_map::ConcurrentLocationCache::new(64);
+        assert_eq!(m.shards(), 16);
+        assert!(m.is_empty());
+
+        let data = random_location_info(1000);
+        thread::scope(|s| {
+            for part in data.chunks(250) {
+                let m = &m;
+                s.spawn(move || {
+                    for (key, value) in part {
+                        m.insert(key.clone(), value.clone());
+                    }
+                });
+            }
+        });
+
+        let unique: HashSet<&String> = data.iter().map(|(k, _)| k).collect();
+        assert_eq!(m.len(), unique.len());
+        for (key, _) in data.iter() {
+            assert!(m.contains_key(key));
+            assert_eq!(m.with(key, |v| v.name.clone()), Some(key.clone()));
+        }
+
+        let (key, value) = data[0].clone();
+        assert_eq!(m.get(&key), Some(value.clone()));
+        assert_eq!(m.remove(key.clone()), Some(value));
+        assert_eq!(m.get(&key), None);
+        assert_eq!(m.remove(key), None);
+        assert_eq!(m.len(), unique.len() - 1);
+    }
+
+    #[test]
+    fn sharded_map_spreads_32_bit_hashes() {
+        let m = sharded_map::ShardedMap::with_shards_and_hasher(
+            16,
+            1000,
+            BuildAdler32Hasher::default(),
+        );
+        let data = random_location_info(1000);
+        let used: HashSet<usize> = data.iter().map(|(k, _)| m.shard(k)).collect();
+        assert_eq!(used.len(), 16);
+        for (key, value) in data.iter() {
+            m.insert(key.clone(), value.clone());
+        }
+        assert_eq!(m.get(&data[0].0), Some(data[0].1.clone()));
+
+        let single = sharded_map::ShardedMap::with_shards_and_hasher(
+            1,
+            16,
+            BuildAdler32Hasher::default(),
+        );
+        single.insert(data[0].0.clone(), 1);
+        assert_eq!(single.shard(&data[0].0), 0);
+        assert_eq!(single.get(&data[0].0), Some(1));
+    }
+
+    #[test]
+    fn sharded_map_get_or_insert_with_computes_once() {
+        let m = sharded_map::ShardedMap::new(16);
+        let calls = AtomicUsize::new(0);
+        thread::scope(|s| {
+            for _ in 0..8 {
+                s.spawn(|| {
+                    for i in 0..100 {
+                        let value = m.get_or_insert_with(i, || {
+                            calls.fetch_add(1, Ordering::SeqCst);
+                            i * 2
+                        });
+                        assert_eq!(value, i * 2);
+                    }
+                });
+            }
+        });
+        assert_eq!(calls.load(Ordering::SeqCst), 100);
+        assert_eq!(m.len(), 100);
+        assert_eq!(m.get_or_insert_with(5, || 0), 10);
+    }
+
+    #[test]
+    fn sharded_map_get_or_insert_with_leaves_the_shard_unlocked() {
+        let m = sharded_map::ShardedMap::with_shards_and_hasher(1, 16, RandomState::new());
+        m.insert(1, 10);
+        let value = m.get_or_insert_with(2, || {
+            m.insert(3, 30);
+            m.get(&1).unwrap() + m.get_or_insert_with(4, || 40)
+        });
+        assert_eq!(value, 50);
+        assert_eq!(m.get(&2), Some(50));
+        assert_eq!(m.get(&3), Some(30));
+        assert_eq!(m.len(), 4);
+    }
+
+    thread_local!(
+        static PAUSE: RefCell<Option<(Sender<()>, Receiver<()>)>> = const { RefCell::new(None) }
+    );
+
+    // a key that says when it is cloned, and a value that waits to be let
+    // through when it is, on a thread that set up `PAUSE`
+    #[derive(PartialEq, Eq, Hash, Debug)]
+    struct SignallingKey(u32);
+    #[derive(PartialEq, Debug)]
+    struct PausingValue(u32);
+
+    impl Clone for SignallingKey {
+        fn clone(&self) -> SignallingKey {
+            PAUSE.with(|p| {
+                if let Some((signal, _)) = &*p.borrow() {
+                    let _ = signal.send(());
+                }
+            });
+            SignallingKey(self.0)
+        }
+    }
+
+    impl Clone for PausingValue {
+        fn clone(&self) -> PausingValue {
+            if let Some((_, resume)) = PAUSE.with(|p| p.borrow_mut().take()) {
+                resume.recv().unwrap();
+            }
+            PausingValue(self.0)
+        }
+    }
+
+    #[test]
+    fn sharded_map_get_or_insert_with_does_not_bring_back_removed_values() {
+        let m = sharded_map::ShardedMap::with_shards_and_hasher(1, 16, RandomState::new());
+        let (computing, computing_rx) = channel();
+        let (finish, finish_rx) = channel::<()>();
+        let (registered, registered_rx) = channel();
+        let (resume, resume_rx) = channel();
+        let m = &m;
+        thread::scope(|s| {
+            let first = s.spawn(move || {
+                m.get_or_insert_with(SignallingKey(1), || {
+                    computing.send(()).unwrap();
+                    finish_rx.recv().unwrap();
+                    PausingValue(7)
+                })
+            });
+            computing_rx.recv().unwrap();
+            // waits for the first thread's value, then stops once it has it
+            let second = s.spawn(move || {
+                PAUSE.with(|p| *p.borrow_mut() = Some((registered, resume_rx)));
+                m.get_or_insert_with(SignallingKey(1), || unreachable!())
+            });
+            registered_rx.recv().unwrap();
+            finish.send(()).unwrap();
+            assert_eq!(first.join().unwrap(), PausingValue(7));
+
+            // after the value is stored and its placeholder is gone
+            assert_eq!(m.remove(SignallingKey(1)), Some(PausingValue(7)));
+            resume.send(()).unwrap();
+            assert_eq!(second.join().unwrap(), PausingValue(7));
+        });
+        assert!(!m.contains_key(&SignallingKey(1)));
+        assert_eq!(m.get_or_insert_with(SignallingKey(1), || PausingValue(8)), PausingValue(8));
+    }
+
+    const CONTENDING_THREADS: usize = 4;
+
+    // every thread works through all of the test data, starting at a different place
+    fn contend<F>(data: &[(String, LocationInformation)], op: F)
+    where
+        F: Fn(&String, &LocationInformation) + Sync,
+    {
+        thread::scope(|s| {
+            for t in 0..CONTENDING_THREADS {
+                let op = &op;
+                s.spawn(move || {
+                    let (head, tail) = data.split_at(data.len() / CONTENDING_THREADS * t);
+                    for (key, value) in tail.iter().chain(head) {
+                        op(key, value);
+                    }
+                });
+            }
+        });
+    }
+
+    #[bench]
+    fn bench_sharded_map_contended_reads(b: &mut Bencher) {
+        let data = random_location_info(NO_ITEMS);
+        let m = sharded_map::ConcurrentLocationCache::new(NO_ITEMS);
+        for (key, value) in data.iter() {
+            m.insert(key.clone(), value.clone());
+        }
+        b.iter(|| contend(&data, |k, _| assert!(m.contains_key(k))));
+    }
+
+    #[bench]
+    fn bench_mutex_map_contended_reads(b: &mut Bencher) {
+        let data = random_location_info(NO_ITEMS);
+        let m = Mutex::new(map::LocationCache::new(NO_ITEMS));
+        for (key, value) in data.iter() {
+            m.lock().unwrap().insert(key.clone(), value.clone());
+        }
+        b.iter(|| contend(&data, |k, _| assert!(m.lock().unwrap().contains_key(k))));
+    }
+
+    #[bench]
+    fn bench_sharded_map_contended_writes(b: &mut Bencher) {
+        let data = random_location_info(NO_ITEMS);
+        let m = sharded_map::ConcurrentLocationCache::new(NO_ITEMS);
+        b.iter(|| contend(&data, |k, v| drop(m.insert(k.clone(), v.clone()))));
+    }
+
+    #[bench]
+    fn bench_single_shard_map_contended_writes(b: &mut Bencher) {
+        let data = random_location_info(NO_ITEMS);
+        let m = sharded_map::ShardedMap::with_shards_and_hasher(1, NO_ITEMS, RandomState::new());
+        b.iter(|| contend(&data, |k, v| drop(m.insert(k.clone(), v.clone()))));
+    }
+
+    #[bench]
+    fn bench_mutex_map_contended_writes(b: &mut Bencher) {
+        let data = random_location_info(NO_ITEMS);
+        let m = Mutex::new(map::LocationCache::new(NO_ITEMS));
+        b.iter(|| contend(&data, |k, v| drop(m.lock().unwrap().insert(k.clone(), v.clone()))));
+    }
+
+    #[bench]
+    fn bench_hash_map_insert_growing(b: &mut Bencher) {
+        TEST_DATA.with(|location_info_cell| {
+            let location_info = location_info_cell.borrow();
+            b.iter(|| {
+                let mut m = map::LocationCache::with_hasher(1, BuildAdler32Hasher::default());
+                for (key, value) in location_info.iter() {
+                    m.insert(key.clone(), value.clone());
+                }
+                m.length
+            });
+        });
+    }
+
+    #[bench]
+    fn bench_std_hash_map_insert_growing(b: &mut Bencher) {
+        TEST_DATA.with(|location_info_cell| {
+            let location_info = location_info_cell.borrow();
+            b.iter(|| {
+                let mut m = std::collections::HashMap::new();
+                for (key, value) in location_info.iter() {
+                    m.insert(key.clone(), value.clone());
+                }
+                m.len()
+            });
+        });
+    }
+
+    macro_rules! open_addressing_tests {
+        ($name:ident, $($map:ident)::+) => {
+            mod $name {
+                use super::*;
+
+                #[test]
+                fn insert_get_remove() {
+                    let mut m = $($map)::+::with_hasher(3, BuildLengthHasher::default());
+                    let val = random_location_info(3);
+                    for (key, value) in val.iter() {
+                        m.insert(key.clone(), value.clone());
+                    }
+                    assert_eq!(
+                        m.insert(val[0].0.clone(), val[1].1.clone()),
+                        Some(val[0].1.clone())
+                    );
+                    assert_eq!(m.length, val.len());
+
+                    assert_eq!(m.get(&val[0].0), Some(&val[1].1));
+                    assert_eq!(m.get(&"Naxxramas".to_owned()), None);
+                    assert_eq!(m.remove(val[1].0.clone()), Some(val[1].1.clone()));
+                    assert_eq!(m.remove(val[1].0.clone()), None);
+                    assert_eq!(m.get(&val[2].0), Some(&val[2].1));
+                    assert_eq!(m.length, 2);
+                }
+
+                fn matches_std<S: BuildHasher>(hash_builder: S) {
+                    let mut m = $($map)::+::with_hasher(1, hash_builder);
+                    let mut expected = std::collections::HashMap::new();
+                    let mut rng = thread_rng();
+                    let items = random_location_info(2_000);
+
+                    for (key, value) in items.iter() {
+                        assert_eq!(
+                            m.insert(key.clone(), value.clone()),
+                            expected.insert(key.clone(), value.clone())
+                        );
+                        let (other, _) = rng.choose(&items).unwrap();
+                        if rng.gen_range(0, 3) == 0 {
+                            assert_eq!(m.remove(other.clone()), expected.remove(other));
+                        }
+                        assert_eq!(m.get(other), expected.get(other));
+                        assert_eq!(m.length, expected.len());
+                    }
+                    for (key, _) in items.iter() {
+                        assert_eq!(m.get(key), expected.get(key));
+                    }
+                }
+
+                #[test]
+                fn matches_std_hash_map() {
+                    matches_std(BuildAdler32Hasher::default());
+                }
+
+                #[test]
+                fn matches_std_hash_map_with_collisions() {
+                    matches_std(BuildLengthHasher::default());
+                }
+            }
+        };
+    }
+
+    open_addressing_tests!(robin_hood_map_tests, robin_hood_map::RobinHoodMap);
+    open_addressing_tests!(group_map_tests, group_map::GroupProbingMap);
+
+    #[bench]
+    fn bench_robin_hood_map_defaulthasher(b: &mut Bencher) {
+        TEST_DATA.with(|location_info_cell| {
+            let location_info = location_info_cell.borrow();
+            let mut m = robin_hood_map::RobinHoodMap::with_hasher(
+                1,
+                BuildHasherDefault::<DefaultHasher>::default(),
+            );
+            for (key, value) in location_info.iter() {
+                m.insert(key.clone(), value.clone());
+            }
+
+            let mut rng = thread_rng();
+            b.iter(|| {
+                let pair = rng.choose(&location_info).expect("Nothing to choose from");
+                m.get(&pair.0).expect("Not Found");
+            });
+        });
+    }
+
+    #[bench]
+    fn bench_group_map_defaulthasher(b: &mut Bencher) {
+        TEST_DATA.with(|location_info_cell| {
+            let location_info = location_info_cell.borrow();
+            let mut m = group_map::GroupProbingMap::with_hasher(
+                1,
+                BuildHasherDefault::<DefaultHasher>::default(),
+            );
+            for (key, value) in location_info.iter() {
+                m.insert(key.clone(), value.clone());
+            }
+
+            let mut rng = thread_rng();
+            b.iter(|| {
+                let pair = rng.choose(&location_info).expect("Nothing to choose from");
+                m.get(&pair.0).expect("Not Found");
+            });
+        });
+    }
+
+    #[bench]
+    fn bench_robin_hood_map_insert_growing(b: &mut Bencher) {
+        TEST_DATA.with(|location_info_cell| {
+            let location_info = location_info_cell.borrow();
+            b.iter(|| {
+                let mut m =
+                    robin_hood_map::RobinHoodMap::with_hasher(1, BuildAdler32Hasher::default());
+                for (key, value) in location_info.iter() {
+                    m.insert(key.clone(), value.clone());
+                }
+                m.length
+            });
+        });
+    }
+
+    #[bench]
+    fn bench_group_map_insert_growing(b: &mut Bencher) {
+        TEST_DATA.with(|location_info_cell| {
+            let location_info = location_info_cell.borrow();
+            b.iter(|| {
+                let mut m =
+                    group_map::GroupProbingMap::with_hasher(1, BuildAdler32Hasher::default());
+                for (key, value) in location_info.iter() {
+                    m.insert(key.clone(), value.clone());
+                }
+                m.length
+            });
+        });
+    }
 }
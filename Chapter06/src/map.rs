@@ -1,62 +1,357 @@
 use crate::LocationInformation;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash};
+use std::iter::FromIterator;
+use std::mem;
 
-type Entry<K, V> = Vec<(K, V)>;
+type Bucket<K, V> = Vec<(K, V)>;
 
-pub type LocationCache = HashMap<String, LocationInformation>;
+pub type LocationCache<S = RandomState> = HashMap<String, LocationInformation, S>;
 
-pub struct HashMap<K, V>
+const DEFAULT_LOAD_FACTOR: f64 = 0.75;
+
+// old buckets moved into the new table by every call to insert or remove
+const REHASH_STEP: usize = 4;
+
+pub struct HashMap<K, V, S = RandomState> {
+    hash_builder: S,
+    store: Box<[Bucket<K, V>]>,
+    // while resizing, the buckets from `migrated` on still live here
+    old_store: Box<[Bucket<K, V>]>,
+    migrated: usize,
+    max_load_factor: f64,
+    pub length: usize,
+}
+
+/// A single key's place in the map, which may or may not hold a value.
+pub enum Entry<'a, K, V, S> {
+    Occupied(OccupiedEntry<'a, K, V>),
+    Vacant(VacantEntry<'a, K, V, S>),
+}
+
+pub struct OccupiedEntry<'a, K, V> {
+    pair: &'a mut (K, V),
+}
+
+pub struct VacantEntry<'a, K, V, S> {
+    map: &'a mut HashMap<K, V, S>,
+    key: K,
+}
+
+fn empty_store<K, V>(buckets: usize) -> Box<[Bucket<K, V>]> {
+    (0..buckets).map(|_| vec![]).collect()
+}
+
+fn buckets_for(capacity: usize, load_factor: f64) -> usize {
+    ((capacity as f64 / load_factor).ceil() as usize).next_power_of_two()
+}
+
+impl<K, V> HashMap<K, V, RandomState>
 where
-    K: PartialEq + Clone,
-    V: Clone,
+    K: Hash + Eq,
 {
-    hash_fn: Box<dyn (Fn(&K) -> usize)>,
-    store: Box<[Entry<K, V>]>,
-    pub length: usize,
+    /// Creates a map with `length` buckets, rounded up to a power of two.
+    pub fn new(length: usize) -> HashMap<K, V> {
+        HashMap::with_hasher(length, RandomState::new())
+    }
+
+    /// Creates a map that holds `capacity` entries without resizing.
+    pub fn with_capacity(capacity: usize) -> HashMap<K, V> {
+        HashMap::with_capacity_and_hasher(capacity, RandomState::new())
+    }
 }
 
-impl<K, V> HashMap<K, V>
+impl<K, V, S> HashMap<K, V, S>
 where
-    K: PartialEq + Clone,
-    V: Clone,
+    K: Hash + Eq,
+    S: BuildHasher,
 {
-    pub fn new(hash_fn: Box<dyn (Fn(&K) -> usize)>, length: usize) -> HashMap<K, V> {
+    /// Like `new`, but hashes keys with `hash_builder`.
+    pub fn with_hasher(length: usize, hash_builder: S) -> HashMap<K, V, S> {
         HashMap {
-            hash_fn: hash_fn,
+            hash_builder,
             length: 0,
-            store: vec![vec![]; length].into_boxed_slice(),
+            store: empty_store(length.next_power_of_two()),
+            old_store: empty_store(0),
+            migrated: 0,
+            max_load_factor: DEFAULT_LOAD_FACTOR,
         }
     }
 
-    pub fn get(&self, key: &K) -> Option<V> {
-        let h = (self.hash_fn)(key);
-        let idx = h & (self.store.len() - 1);
-        self.store[idx]
-            .iter()
+    pub fn with_capacity_and_hasher(capacity: usize, hash_builder: S) -> HashMap<K, V, S> {
+        HashMap::with_hasher(buckets_for(capacity, DEFAULT_LOAD_FACTOR), hash_builder)
+    }
+
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
+    }
+
+    pub fn buckets(&self) -> usize {
+        self.store.len()
+    }
+
+    /// The number of entries before the map has to grow.
+    pub fn capacity(&self) -> usize {
+        (self.store.len() as f64 * self.max_load_factor) as usize
+    }
+
+    pub fn load_factor(&self) -> f64 {
+        self.length as f64 / self.store.len() as f64
+    }
+
+    /// Sets the average chain length that triggers a resize.
+    pub fn set_max_load_factor(&mut self, load_factor: f64) {
+        assert!(load_factor > 0.0, "the load factor has to be positive");
+        self.max_load_factor = load_factor;
+        self.grow_if_needed(0);
+    }
+
+    pub fn is_resizing(&self) -> bool {
+        self.migrated < self.old_store.len()
+    }
+
+    /// Makes room for at least `additional` more entries.
+    pub fn reserve(&mut self, additional: usize) {
+        self.grow_if_needed(additional);
+    }
+
+    /// Resizes to the fewest buckets that fit the current entries.
+    pub fn shrink_to_fit(&mut self) {
+        let buckets = buckets_for(self.length, self.max_load_factor);
+        if buckets < self.store.len() {
+            self.resize(buckets);
+        }
+    }
+
+    fn grow_if_needed(&mut self, additional: usize) {
+        let required = self.length + additional;
+        if required > self.capacity() {
+            let buckets = buckets_for(required, self.max_load_factor);
+            self.resize(buckets.max(self.store.len() * 2));
+        }
+    }
+
+    fn resize(&mut self, buckets: usize) {
+        // finishing a pending resize first keeps it to two tables at most
+        while self.is_resizing() {
+            self.rehash_step();
+        }
+        self.old_store = mem::replace(&mut self.store, empty_store(buckets));
+        self.migrated = 0;
+    }
+
+    fn rehash_step(&mut self) {
+        let end = (self.migrated + REHASH_STEP).min(self.old_store.len());
+        for i in self.migrated..end {
+            for (key, value) in mem::take(&mut self.old_store[i]) {
+                let idx = self.index(&key, self.store.len());
+                self.store[idx].push((key, value));
+            }
+        }
+        self.migrated = end;
+        if !self.is_resizing() {
+            self.old_store = empty_store(0);
+            self.migrated = 0;
+        }
+    }
+
+    fn index(&self, key: &K, buckets: usize) -> usize {
+        self.hash_builder.hash_one(key) as usize & (buckets - 1)
+    }
+
+    // the bucket holding `key`, whether it has been moved or not
+    fn bucket(&self, key: &K) -> &Bucket<K, V> {
+        if self.is_resizing() {
+            let idx = self.index(key, self.old_store.len());
+            if idx >= self.migrated {
+                return &self.old_store[idx];
+            }
+        }
+        &self.store[self.index(key, self.store.len())]
+    }
+
+    fn bucket_mut(&mut self, key: &K) -> &mut Bucket<K, V> {
+        if self.is_resizing() {
+            let idx = self.index(key, self.old_store.len());
+            if idx >= self.migrated {
+                return &mut self.old_store[idx];
+            }
+        }
+        let idx = self.index(key, self.store.len());
+        &mut self.store[idx]
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.bucket(key).iter().find(|e| e.0 == *key).map(|e| &e.1)
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.bucket_mut(key)
+            .iter_mut()
             .find(|e| e.0 == *key)
-            .map(|e| e.1.clone())
+            .map(|e| &mut e.1)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
     }
 
     pub fn remove(&mut self, key: K) -> Option<V> {
-        let h = (self.hash_fn)(&key);
-        let idx = h & (self.store.len() - 1);
-        match self.store[idx].iter().position(|e| e.0 == key) {
+        if self.is_resizing() {
+            self.rehash_step();
+        }
+        let bucket = self.bucket_mut(&key);
+        match bucket.iter().position(|e| e.0 == key) {
             Some(pos) => {
+                let (_, value) = bucket.swap_remove(pos);
                 self.length -= 1;
-                Some(self.store[idx].remove(pos).1)
+                Some(value)
             }
             _ => None,
         }
     }
 
-    pub fn insert(&mut self, key: K, value: V) {
-        let h = (self.hash_fn)(&key);
-        let idx = h & (self.store.len() - 1);
-        match self.store[idx].iter().position(|e| e.0 == key) {
-            Some(pos) => self.store[idx][pos] = (key, value),
-            None => {
-                self.store[idx].push((key, value));
-                self.length += 1
+    /// Returns the value that was replaced, if any.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.entry(key) {
+            Entry::Occupied(entry) => Some(mem::replace(entry.into_mut(), value)),
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+                None
             }
         }
     }
+
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V, S> {
+        if self.is_resizing() {
+            self.rehash_step();
+        }
+        match self.bucket(&key).iter().position(|e| e.0 == key) {
+            Some(pos) => Entry::Occupied(OccupiedEntry {
+                pair: &mut self.bucket_mut(&key)[pos],
+            }),
+            None => Entry::Vacant(VacantEntry { map: self, key }),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.store
+            .iter()
+            .chain(self.old_store.iter())
+            .flatten()
+            .map(|(k, v)| (k, v))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&K, &mut V)> {
+        self.store
+            .iter_mut()
+            .chain(self.old_store.iter_mut())
+            .flatten()
+            .map(|(k, v)| (&*k, v))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.iter().map(|(k, _)| k)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.iter().map(|(_, v)| v)
+    }
+
+    /// Removes all entries, keeping the buckets allocated. Those of a
+    /// resize that is still going on are released.
+    pub fn drain(&mut self) -> impl Iterator<Item = (K, V)> {
+        let old_store = mem::replace(&mut self.old_store, empty_store(0));
+        self.migrated = 0;
+        self.length = 0;
+        let entries: Vec<(K, V)> = self
+            .store
+            .iter_mut()
+            .flat_map(|bucket| bucket.drain(..))
+            .chain(old_store.into_vec().into_iter().flatten())
+            .collect();
+        entries.into_iter()
+    }
+}
+
+impl<'a, K, V, S> Entry<'a, K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => &entry.pair.0,
+            Entry::Vacant(entry) => &entry.key,
+        }
+    }
+
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with(self, default: impl FnOnce() -> V) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    pub fn and_modify(mut self, f: impl FnOnce(&mut V)) -> Entry<'a, K, V, S> {
+        if let Entry::Occupied(ref mut entry) = self {
+            f(&mut entry.pair.1);
+        }
+        self
+    }
+}
+
+impl<'a, K, V> OccupiedEntry<'a, K, V> {
+    pub fn get(&self) -> &V {
+        &self.pair.1
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        &mut self.pair.1
+    }
+}
+
+impl<'a, K, V, S> VacantEntry<'a, K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    pub fn insert(self, value: V) -> &'a mut V {
+        let map = self.map;
+        // growing first keeps the new entry in place
+        map.grow_if_needed(1);
+        map.length += 1;
+        let bucket = map.bucket_mut(&self.key);
+        bucket.push((self.key, value));
+        &mut bucket.last_mut().expect("The entry was just added").1
+    }
+}
+
+impl<K, V, S> Extend<(K, V)> for HashMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K, V, S> FromIterator<(K, V)> for HashMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Default,
+{
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> HashMap<K, V, S> {
+        let iter = iter.into_iter();
+        let mut map = HashMap::with_capacity_and_hasher(iter.size_hint().0, S::default());
+        map.extend(iter);
+        map
+    }
 }
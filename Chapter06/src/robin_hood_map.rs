@@ -0,0 +1,151 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash};
+use std::mem;
+
+// Fibonacci hashing spreads weak hashes (like adler32 on short keys) over the
+// table, which linear probing depends on
+const SPREAD: u64 = 0x9E37_79B9_7F4A_7C15;
+
+struct Slot<K, V> {
+    hash: usize,
+    key: K,
+    value: V,
+}
+
+/// Open addressing with linear probing, where an insert takes the slot of
+/// any entry that is closer to its home bucket.
+pub struct RobinHoodMap<K, V, S = RandomState> {
+    hash_builder: S,
+    slots: Vec<Option<Slot<K, V>>>,
+    pub length: usize,
+}
+
+impl<K, V> RobinHoodMap<K, V, RandomState>
+where
+    K: Hash + Eq,
+{
+    /// Creates a map with `length` slots, rounded up to a power of two.
+    pub fn new(length: usize) -> RobinHoodMap<K, V> {
+        RobinHoodMap::with_hasher(length, RandomState::new())
+    }
+}
+
+impl<K, V, S> RobinHoodMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Like `new`, but hashes keys with `hash_builder`.
+    pub fn with_hasher(length: usize, hash_builder: S) -> RobinHoodMap<K, V, S> {
+        RobinHoodMap {
+            hash_builder,
+            slots: (0..length.next_power_of_two()).map(|_| None).collect(),
+            length: 0,
+        }
+    }
+
+    fn mask(&self) -> usize {
+        self.slots.len() - 1
+    }
+
+    fn home(&self, hash: usize) -> usize {
+        ((hash as u64).wrapping_mul(SPREAD) >> 32) as usize & self.mask()
+    }
+
+    // how far the entry in `idx` is from its home slot
+    fn distance(&self, hash: usize, idx: usize) -> usize {
+        idx.wrapping_sub(self.home(hash)) & self.mask()
+    }
+
+    fn find(&self, key: &K) -> Option<usize> {
+        let hash = self.hash_builder.hash_one(key) as usize;
+        let mut idx = self.home(hash);
+        for dist in 0..self.slots.len() {
+            match self.slots[idx] {
+                // a richer entry means the key would have been placed before it
+                Some(ref slot) if self.distance(slot.hash, idx) >= dist => {
+                    if slot.hash == hash && slot.key == *key {
+                        return Some(idx);
+                    }
+                }
+                _ => return None,
+            }
+            idx = (idx + 1) & self.mask();
+        }
+        None
+    }
+
+    fn place(&mut self, mut slot: Slot<K, V>) {
+        let mut idx = self.home(slot.hash);
+        let mut dist = 0;
+        loop {
+            let existing_dist = match self.slots[idx] {
+                Some(ref existing) => self.distance(existing.hash, idx),
+                None => {
+                    self.slots[idx] = Some(slot);
+                    return;
+                }
+            };
+            if existing_dist < dist {
+                if let Some(ref mut existing) = self.slots[idx] {
+                    mem::swap(existing, &mut slot);
+                }
+                dist = existing_dist;
+            }
+            idx = (idx + 1) & self.mask();
+            dist += 1;
+        }
+    }
+
+    fn grow(&mut self) {
+        let size = self.slots.len() * 2;
+        let old = mem::replace(&mut self.slots, (0..size).map(|_| None).collect());
+        for slot in old.into_iter().flatten() {
+            self.place(slot);
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.find(key)
+            .and_then(|idx| self.slots[idx].as_ref())
+            .map(|slot| &slot.value)
+    }
+
+    pub fn remove(&mut self, key: K) -> Option<V> {
+        let mut idx = self.find(&key)?;
+        let removed = self.slots[idx].take();
+        self.length -= 1;
+
+        // backward shift: move followers one slot closer to home, no tombstones
+        loop {
+            let next = (idx + 1) & self.mask();
+            match self.slots[next] {
+                Some(ref slot) if self.distance(slot.hash, next) > 0 => {
+                    self.slots[idx] = self.slots[next].take();
+                    idx = next;
+                }
+                _ => break,
+            }
+        }
+        removed.map(|slot| slot.value)
+    }
+
+    /// Returns the value that was replaced, if any.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.find(&key) {
+            Some(idx) => self.slots[idx]
+                .as_mut()
+                .map(|slot| mem::replace(&mut slot.value, value)),
+            None => {
+                // keep the table at most 7/8 full
+                if (self.length + 1) * 8 > self.slots.len() * 7 {
+                    self.grow();
+                }
+                let hash = self.hash_builder.hash_one(&key) as usize;
+                self.place(Slot { hash, key, value });
+                self.length += 1;
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use crate::MOD_ADLER;
+use std::collections::VecDeque;
+
+// spreads the checksum before masking, since `a` alone is a plain byte sum
+const SPREAD: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// `adler32` over a window that can move along a stream one byte at a time,
+/// like rsync's weak checksum.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RollingAdler32 {
+    a: u32,
+    b: u32,
+    pub length: usize,
+}
+
+impl Default for RollingAdler32 {
+    fn default() -> RollingAdler32 {
+        RollingAdler32 {
+            a: 1,
+            b: 0,
+            length: 0,
+        }
+    }
+}
+
+impl RollingAdler32 {
+    /// Starts with `bytes` as the window.
+    pub fn new(bytes: &[u8]) -> RollingAdler32 {
+        let mut checksum = RollingAdler32::default();
+        for b in bytes {
+            checksum.roll_in(*b);
+        }
+        checksum
+    }
+
+    pub fn hash(&self) -> u32 {
+        (self.b << 16) | self.a
+    }
+
+    /// Appends `byte` to the window.
+    pub fn roll_in(&mut self, byte: u8) {
+        self.a = (self.a + byte as u32) % MOD_ADLER;
+        self.b = (self.b + self.a) % MOD_ADLER;
+        self.length += 1;
+    }
+
+    /// Drops `byte`, which has to be the window's first byte.
+    pub fn roll_out(&mut self, byte: u8) {
+        // the first byte went into `b` once per byte in the window, plus the 1 of `a`
+        let weight = (self.length % MOD_ADLER as usize) as u32 * byte as u32 % MOD_ADLER;
+        self.a = (self.a + MOD_ADLER - byte as u32) % MOD_ADLER;
+        self.b = (self.b + 2 * MOD_ADLER - weight - 1) % MOD_ADLER;
+        self.length -= 1;
+    }
+
+    /// Moves a window of the same length one byte further.
+    pub fn roll(&mut self, old: u8, new: u8) {
+        self.roll_out(old);
+        self.roll_in(new);
+    }
+}
+
+/// Splits a stream wherever the rolling checksum of the last `window_size`
+/// bytes hits a pattern, so an edit only moves the boundaries close to it.
+pub struct Chunker {
+    checksum: RollingAdler32,
+    window: VecDeque<u8>,
+    window_size: usize,
+    mask: u64,
+    min_size: usize,
+    max_size: usize,
+    chunk_start: usize,
+    offset: usize,
+}
+
+impl Chunker {
+    /// `average_size` is rounded up to a power of two; chunks are cut at
+    /// `max_size` at the latest and never before `min_size`.
+    pub fn new(
+        window_size: usize,
+        min_size: usize,
+        average_size: usize,
+        max_size: usize,
+    ) -> Chunker {
+        assert!(window_size > 0, "the window can't be empty");
+        assert!(
+            min_size <= average_size && average_size <= max_size,
+            "chunk sizes have to be ascending"
+        );
+        Chunker {
+            checksum: RollingAdler32::default(),
+            window: VecDeque::with_capacity(window_size),
+            window_size,
+            mask: average_size.next_power_of_two() as u64 - 1,
+            min_size: min_size.max(1),
+            max_size,
+            chunk_start: 0,
+            offset: 0,
+        }
+    }
+
+    fn is_boundary(&self) -> bool {
+        let size = self.offset - self.chunk_start;
+        if size >= self.max_size {
+            true
+        } else if size < self.min_size || self.window.len() < self.window_size {
+            false
+        } else {
+            let spread = (self.checksum.hash() as u64).wrapping_mul(SPREAD) >> 32;
+            spread & self.mask == self.mask
+        }
+    }
+
+    /// Feeds the next bytes of the stream and returns the offsets where
+    /// chunks end, counted from the start of the stream.
+    pub fn update(&mut self, bytes: &[u8]) -> Vec<usize> {
+        let mut boundaries = vec![];
+        for b in bytes {
+            if self.window.len() == self.window_size {
+                let old = self.window.pop_front().expect("The window is full");
+                self.checksum.roll(old, *b);
+            } else {
+                self.checksum.roll_in(*b);
+            }
+            self.window.push_back(*b);
+            self.offset += 1;
+
+            if self.is_boundary() {
+                boundaries.push(self.offset);
+                self.chunk_start = self.offset;
+            }
+        }
+        boundaries
+    }
+
+    /// Ends the stream, returning the end of the last chunk unless it's empty.
+    pub fn finish(&mut self) -> Option<usize> {
+        let last = self.offset;
+        let end = if last > self.chunk_start {
+            Some(last)
+        } else {
+            None
+        };
+        self.chunk_start = last;
+        end
+    }
+
+    /// The end of each chunk of a complete stream.
+    pub fn boundaries(&mut self, bytes: &[u8]) -> Vec<usize> {
+        let mut boundaries = self.update(bytes);
+        boundaries.extend(self.finish());
+        boundaries
+    }
+}
@@ -0,0 +1,174 @@
+use crate::map::{Entry, HashMap};
+use crate::LocationInformation;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash};
+use std::sync::{Arc, Mutex, OnceLock, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+pub type ConcurrentLocationCache<S = RandomState> = ShardedMap<String, LocationInformation, S>;
+
+// placeholders for values that are still being computed
+type Pending<K, V, S> = Mutex<HashMap<K, Arc<OnceLock<V>>, S>>;
+
+const DEFAULT_SHARDS: usize = 16;
+// Fibonacci hashing's multiplier, 2^64 / the golden ratio
+const SPREAD: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// A `map::HashMap` split into shards that are locked separately, so
+/// threads only wait for each other when they use the same shard.
+pub struct ShardedMap<K, V, S = RandomState> {
+    hash_builder: S,
+    shards: Box<[RwLock<HashMap<K, V, S>>]>,
+    // what `get_or_insert_with` is computing, per shard
+    pending: Box<[Pending<K, V, S>]>,
+}
+
+impl<K, V> ShardedMap<K, V, RandomState>
+where
+    K: Hash + Eq,
+{
+    /// Creates a map with `length` buckets in total, spread over the shards.
+    pub fn new(length: usize) -> ShardedMap<K, V> {
+        ShardedMap::with_shards_and_hasher(DEFAULT_SHARDS, length, RandomState::new())
+    }
+}
+
+impl<K, V, S> ShardedMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Clone,
+{
+    /// `shards` is rounded up to a power of two.
+    pub fn with_shards_and_hasher(
+        shards: usize,
+        length: usize,
+        hash_builder: S,
+    ) -> ShardedMap<K, V, S> {
+        let shards = shards.next_power_of_two();
+        ShardedMap {
+            shards: (0..shards)
+                .map(|_| RwLock::new(HashMap::with_hasher(length / shards, hash_builder.clone())))
+                .collect(),
+            pending: (0..shards)
+                .map(|_| Mutex::new(HashMap::with_hasher(1, hash_builder.clone())))
+                .collect(),
+            hash_builder,
+        }
+    }
+
+    pub fn shards(&self) -> usize {
+        self.shards.len()
+    }
+
+    // the buckets use the low bits of the hash, which 32 bit hashers also
+    // leave as the only ones set, so the shards take the top bits of it spread
+    pub(crate) fn shard(&self, key: &K) -> usize {
+        let spread = self.hash_builder.hash_one(key).wrapping_mul(SPREAD);
+        let bits = self.shards.len().trailing_zeros();
+        spread.checked_shr(64 - bits).unwrap_or(0) as usize
+    }
+
+    fn read(&self, key: &K) -> RwLockReadGuard<'_, HashMap<K, V, S>> {
+        self.shards[self.shard(key)]
+            .read()
+            .expect("A thread panicked while holding the shard")
+    }
+
+    fn write(&self, key: &K) -> RwLockWriteGuard<'_, HashMap<K, V, S>> {
+        self.shards[self.shard(key)]
+            .write()
+            .expect("A thread panicked while holding the shard")
+    }
+
+    /// The number of entries, which may already be outdated.
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|s| {
+                s.read()
+                    .expect("A thread panicked while holding the shard")
+                    .length
+            })
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, key: &K) -> Option<V>
+    where
+        V: Clone,
+    {
+        self.read(key).get(key).cloned()
+    }
+
+    /// Calls `f` with the value while holding the shard's read lock.
+    pub fn with<R>(&self, key: &K, f: impl FnOnce(&V) -> R) -> Option<R> {
+        self.read(key).get(key).map(f)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.read(key).contains_key(key)
+    }
+
+    /// Returns the value that was replaced, if any.
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        self.write(&key).insert(key, value)
+    }
+
+    pub fn remove(&self, key: K) -> Option<V> {
+        self.write(&key).remove(key)
+    }
+
+    /// Returns the value for `key`, calling `default` if there is none. Other
+    /// threads asking for the same key wait for that call instead of making
+    /// their own, while the rest of the shard stays available. `default` must
+    /// not ask for the same key again, which would wait for itself.
+    pub fn get_or_insert_with(&self, key: K, default: impl FnOnce() -> V) -> V
+    where
+        K: Clone,
+        V: Clone,
+    {
+        let shard = self.shard(&key);
+        let cell = {
+            // the shard is locked before `pending` everywhere, and values
+            // replace their placeholder under the shard's write lock, so
+            // this sees one or the other
+            let values = self.read(&key);
+            if let Some(value) = values.get(&key) {
+                return value.clone();
+            }
+            let mut pending = self.pending[shard]
+                .lock()
+                .expect("A thread panicked while holding the shard");
+            pending
+                .entry(key.clone())
+                .or_insert_with(|| Arc::new(OnceLock::new()))
+                .clone()
+        };
+
+        let mut computed = false;
+        let value = cell
+            .get_or_init(|| {
+                computed = true;
+                default()
+            })
+            .clone();
+        if !computed {
+            // only the thread that computed the value inserts it, since it may
+            // already have been inserted and removed again
+            return self.get(&key).unwrap_or(value);
+        }
+
+        let mut values = self.write(&key);
+        let mut pending = self.pending[shard]
+            .lock()
+            .expect("A thread panicked while holding the shard");
+        pending.remove(key.clone());
+        match values.entry(key) {
+            // inserted by `insert` in the meantime
+            Entry::Occupied(entry) => entry.get().clone(),
+            Entry::Vacant(entry) => entry.insert(value).clone(),
+        }
+    }
+}
@@ -0,0 +1,318 @@
+//! A binary format for `LocationCache` snapshots, all integers little endian:
+//!
+//! ```text
+//! "LOCS" | major: u8 | minor: u8 | header length: u16 | entries: u64 | ...
+//! (record length: u32 | (field length: u32 | UTF-8)* )*
+//! adler32 of everything before it: u32
+//! ```
+//!
+//! A record holds the key and the `LocationInformation` fields in order.
+//! Later minor versions may only append to the header and the records, which
+//! older readers skip thanks to the length prefixes. A new major version
+//! breaks that promise.
+
+use crate::map::LocationCache;
+use crate::{Adler32Hasher, LocationInformation};
+use std::error::Error;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, Read, Write};
+
+pub const MAGIC: [u8; 4] = *b"LOCS";
+pub const MAJOR_VERSION: u8 = 1;
+pub const MINOR_VERSION: u8 = 0;
+
+// what version 1.0 puts into the header after its length
+const HEADER_LENGTH: u16 = 8;
+const FIELDS: usize = 5;
+
+// keeps corrupted lengths from allocating everything there is
+const MAX_RECORD_LENGTH: usize = 1 << 20;
+const MAX_PREALLOCATED: usize = 1 << 16;
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    NotASnapshot,
+    /// The major and minor version of a snapshot this reader can't handle.
+    UnsupportedVersion(u8, u8),
+    /// The input ended before the trailer.
+    Truncated,
+    Corrupt(String),
+    ChecksumMismatch {
+        expected: u32,
+        actual: u32,
+    },
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Io(e) => write!(f, "I/O error: {}", e),
+            SnapshotError::NotASnapshot => write!(f, "not a snapshot"),
+            SnapshotError::UnsupportedVersion(major, minor) => {
+                write!(f, "unsupported snapshot version {}.{}", major, minor)
+            }
+            SnapshotError::Truncated => write!(f, "the snapshot ends early"),
+            SnapshotError::Corrupt(message) => write!(f, "corrupt snapshot: {}", message),
+            SnapshotError::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "checksum mismatch: expected {:08x}, got {:08x}",
+                expected, actual
+            ),
+        }
+    }
+}
+
+impl Error for SnapshotError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SnapshotError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(e: io::Error) -> SnapshotError {
+        if e.kind() == io::ErrorKind::UnexpectedEof {
+            SnapshotError::Truncated
+        } else {
+            SnapshotError::Io(e)
+        }
+    }
+}
+
+fn corrupt<T>(message: impl Into<String>) -> Result<T, SnapshotError> {
+    Err(SnapshotError::Corrupt(message.into()))
+}
+
+/// Writes the header up front, then one entry at a time.
+pub struct SnapshotWriter<W: Write> {
+    inner: W,
+    checksum: Adler32Hasher,
+    remaining: u64,
+}
+
+impl<W: Write> SnapshotWriter<W> {
+    /// Starts a snapshot of exactly `count` entries.
+    pub fn new(inner: W, count: usize) -> io::Result<SnapshotWriter<W>> {
+        let mut writer = SnapshotWriter {
+            inner,
+            checksum: Adler32Hasher::default(),
+            remaining: count as u64,
+        };
+        writer.write_bytes(&MAGIC)?;
+        writer.write_bytes(&[MAJOR_VERSION, MINOR_VERSION])?;
+        writer.write_bytes(&HEADER_LENGTH.to_le_bytes())?;
+        writer.write_bytes(&(count as u64).to_le_bytes())?;
+        Ok(writer)
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.checksum.write(bytes);
+        self.inner.write_all(bytes)
+    }
+
+    pub fn write_entry(&mut self, key: &str, info: &LocationInformation) -> io::Result<()> {
+        if self.remaining == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "more entries than the header announced",
+            ));
+        }
+        let mut record = vec![];
+        for field in [
+            key,
+            info.name(),
+            info.opened(),
+            info.address(),
+            info.security_group_name(),
+        ] {
+            record.extend_from_slice(&(field.len() as u32).to_le_bytes());
+            record.extend_from_slice(field.as_bytes());
+        }
+        if record.len() > MAX_RECORD_LENGTH {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the entry is too large for a snapshot",
+            ));
+        }
+        self.write_bytes(&(record.len() as u32).to_le_bytes())?;
+        self.write_bytes(&record)?;
+        self.remaining -= 1;
+        Ok(())
+    }
+
+    /// Writes the trailer and hands back the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        if self.remaining > 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "fewer entries than the header announced",
+            ));
+        }
+        let checksum = self.checksum.finish() as u32;
+        self.inner.write_all(&checksum.to_le_bytes())?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+/// Reads entries one at a time. The checksum can only be checked after the
+/// last one, so an iterator that ends without an error is the only proof
+/// that all entries were intact.
+pub struct SnapshotReader<R: Read> {
+    inner: R,
+    checksum: Adler32Hasher,
+    minor_version: u8,
+    remaining: u64,
+    done: bool,
+}
+
+impl<R: Read> SnapshotReader<R> {
+    /// Reads and checks the header.
+    pub fn new(inner: R) -> Result<SnapshotReader<R>, SnapshotError> {
+        let mut reader = SnapshotReader {
+            inner,
+            checksum: Adler32Hasher::default(),
+            minor_version: 0,
+            remaining: 0,
+            done: false,
+        };
+        let start = reader.read_bytes(8).map_err(|e| match e {
+            SnapshotError::Truncated => SnapshotError::NotASnapshot,
+            e => e,
+        })?;
+        if start[..4] != MAGIC {
+            return Err(SnapshotError::NotASnapshot);
+        }
+        if start[4] != MAJOR_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(start[4], start[5]));
+        }
+        reader.minor_version = start[5];
+
+        let header_length = u16::from_le_bytes([start[6], start[7]]);
+        if header_length < HEADER_LENGTH {
+            return corrupt("the header is too short");
+        }
+        let header = reader.read_bytes(header_length as usize)?;
+        let mut count = [0; 8];
+        count.copy_from_slice(&header[..8]);
+        reader.remaining = u64::from_le_bytes(count);
+        Ok(reader)
+    }
+
+    pub fn minor_version(&self) -> u8 {
+        self.minor_version
+    }
+
+    /// The number of entries not read yet.
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+
+    fn read_bytes(&mut self, n: usize) -> Result<Vec<u8>, SnapshotError> {
+        let mut bytes = vec![0; n];
+        self.inner.read_exact(&mut bytes)?;
+        self.checksum.write(&bytes);
+        Ok(bytes)
+    }
+
+    fn read_entry(&mut self) -> Result<(String, LocationInformation), SnapshotError> {
+        let mut length = [0; 4];
+        length.copy_from_slice(&self.read_bytes(4)?);
+        let length = u32::from_le_bytes(length) as usize;
+        if length > MAX_RECORD_LENGTH {
+            return corrupt(format!("a record claims to be {} bytes long", length));
+        }
+        let record = self.read_bytes(length)?;
+
+        let mut fields = Vec::with_capacity(FIELDS);
+        let mut rest = &record[..];
+        while fields.len() < FIELDS {
+            if rest.len() < 4 {
+                return corrupt("a record ends within its fields");
+            }
+            let mut length = [0; 4];
+            length.copy_from_slice(&rest[..4]);
+            let length = u32::from_le_bytes(length) as usize;
+            if rest.len() - 4 < length {
+                return corrupt("a field is longer than its record");
+            }
+            match String::from_utf8(rest[4..4 + length].to_vec()) {
+                Ok(field) => fields.push(field),
+                Err(_) => return corrupt("a field is not valid UTF-8"),
+            }
+            rest = &rest[4 + length..];
+        }
+        // anything left belongs to fields of a later minor version
+
+        let mut fields = fields.into_iter();
+        let mut next = || fields.next().expect("All fields were read");
+        let key = next();
+        Ok((
+            key,
+            LocationInformation::new(next(), next(), next(), next()),
+        ))
+    }
+
+    fn verify(&mut self) -> Result<(), SnapshotError> {
+        let actual = self.checksum.finish() as u32;
+        let mut expected = [0; 4];
+        self.inner.read_exact(&mut expected)?;
+        let expected = u32::from_le_bytes(expected);
+        if expected == actual {
+            Ok(())
+        } else {
+            Err(SnapshotError::ChecksumMismatch { expected, actual })
+        }
+    }
+}
+
+impl<R: Read> Iterator for SnapshotReader<R> {
+    type Item = Result<(String, LocationInformation), SnapshotError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        if self.remaining == 0 {
+            self.done = true;
+            return self.verify().err().map(Err);
+        }
+        match self.read_entry() {
+            Ok(entry) => {
+                self.remaining -= 1;
+                Some(Ok(entry))
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+pub fn write_snapshot<S: BuildHasher, W: Write>(
+    cache: &LocationCache<S>,
+    writer: W,
+) -> io::Result<W> {
+    let mut writer = SnapshotWriter::new(writer, cache.length)?;
+    for (key, info) in cache.iter() {
+        writer.write_entry(key, info)?;
+    }
+    writer.finish()
+}
+
+/// Reads a whole snapshot, failing if any part of it is damaged.
+pub fn read_snapshot<R: Read>(reader: R) -> Result<LocationCache, SnapshotError> {
+    let reader = SnapshotReader::new(reader)?;
+    let count = (reader.remaining() as usize).min(MAX_PREALLOCATED);
+    let mut cache = LocationCache::with_capacity(count);
+    for entry in reader {
+        let (key, info) = entry?;
+        cache.insert(key, info);
+    }
+    Ok(cache)
+}